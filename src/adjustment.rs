@@ -0,0 +1,42 @@
+use std::borrow::Cow;
+
+use nom::number::complete::be_u16;
+use nom::IResult;
+
+use crate::descriptor::{parse_versioned_descriptor, DescriptorValue};
+
+/// The settings of an adjustment layer.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum AdjustmentLayer<'a> {
+    /// Color lookup (`clrL`), applying a 3D LUT or a profile. `name` is the name the LUT was loaded from,
+    /// `lookup_type` the kind of lookup such as `3DLUT`, `abstractProfile` or `deviceLinkProfile`,
+    /// and `data` the embedded `.cube`/`.3dl` file or profile.
+    ColorLookup { name: String, lookup_type: Option<String>, #[cfg_attr(feature = "serde", serde(serialize_with = "crate::serialize::option_len"))] data: Option<Cow<'a, [u8]>> },
+}
+
+impl<'a> AdjustmentLayer<'a> {
+    pub(crate) fn into_static(self) -> AdjustmentLayer<'static> {
+        match self {
+            AdjustmentLayer::ColorLookup { name, lookup_type, data } => AdjustmentLayer::ColorLookup { name, lookup_type, data: data.map(|data| Cow::Owned(data.into_owned())) },
+        }
+    }
+}
+
+pub(crate) fn parse_color_lookup(input: &[u8]) -> IResult<&[u8], AdjustmentLayer<'_>> {
+    let (input, _version) = be_u16(input)?;
+    let (input, descriptor) = parse_versioned_descriptor(input)?;
+    let (mut name, mut file_name, mut lookup_type, mut lut, mut profile) = (None, None, None, None, None);
+    for (key, value) in descriptor.into_items() {
+        match (&*key, value) {
+            ("Nm  ", DescriptorValue::String(value)) => name = Some(value),
+            ("LUT3DFileName", DescriptorValue::String(value)) => file_name = Some(value),
+            ("lookupType", DescriptorValue::Enumerated { value, .. }) => lookup_type = Some(value),
+            ("LUT3DFileData", DescriptorValue::RawData(data)) => lut = Some(data),
+            ("profile", DescriptorValue::RawData(data)) => profile = Some(data),
+            _ => {}
+        }
+    }
+    let name = file_name.or(name).unwrap_or_default();
+    Ok((input, AdjustmentLayer::ColorLookup { name, lookup_type, data: lut.or(profile) }))
+}
@@ -0,0 +1,164 @@
+use crate::layer_info::BlendMode;
+
+/// Luminance as Photoshop computes it for the "Darker Color" / "Lighter Color" family.
+fn luminance([r, g, b]: [f32; 3]) -> f32 {
+    0.3 * r + 0.59 * g + 0.11 * b
+}
+
+/// Moves every component by the same amount so that the luminance becomes `target`, then pulls out-of-range components
+/// back towards the luminance, keeping it exact rather than clamping each channel.
+fn set_luminance(color: [f32; 3], target: f32) -> [f32; 3] {
+    let delta = target - luminance(color);
+    let color = color.map(|component| component + delta);
+    let luminance = luminance(color);
+    let min = color.iter().copied().fold(f32::INFINITY, f32::min);
+    let max = color.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+    if min < 0.0 {
+        color.map(|component| luminance + (component - luminance) * luminance / (luminance - min))
+    } else if max > 1.0 {
+        color.map(|component| luminance + (component - luminance) * (1.0 - luminance) / (max - luminance))
+    } else {
+        color
+    }
+}
+
+fn saturation(color: [f32; 3]) -> f32 {
+    color.iter().copied().fold(f32::NEG_INFINITY, f32::max) - color.iter().copied().fold(f32::INFINITY, f32::min)
+}
+
+/// Rescales the components so that max - min becomes `target`, keeping which component is the largest, middle and smallest.
+fn set_saturation(color: [f32; 3], target: f32) -> [f32; 3] {
+    let mut order = [0, 1, 2];
+    order.sort_by(|&a, &b| color[a].total_cmp(&color[b]));
+    let [min, mid, max] = order;
+    let mut result = [0.0; 3];
+    if color[max] > color[min] {
+        result[mid] = (color[mid] - color[min]) * target / (color[max] - color[min]);
+        result[max] = target;
+    }
+    result
+}
+
+fn separable(mode: BlendMode, backdrop: f32, source: f32) -> f32 {
+    match mode {
+        BlendMode::Darken => backdrop.min(source),
+        BlendMode::Multiply => backdrop * source,
+        BlendMode::Colorburn => {
+            if backdrop >= 1.0 {
+                1.0
+            } else if source <= 0.0 {
+                0.0
+            } else {
+                1.0 - ((1.0 - backdrop) / source).min(1.0)
+            }
+        }
+        BlendMode::Linearburn => (backdrop + source - 1.0).max(0.0),
+        BlendMode::Lighten => backdrop.max(source),
+        BlendMode::Screen => backdrop + source - backdrop * source,
+        BlendMode::Colordodge => {
+            if backdrop <= 0.0 {
+                0.0
+            } else if source >= 1.0 {
+                1.0
+            } else {
+                (backdrop / (1.0 - source)).min(1.0)
+            }
+        }
+        BlendMode::Lineardodge => (backdrop + source).min(1.0),
+        BlendMode::Overlay => separable(BlendMode::Hardlight, source, backdrop),
+        BlendMode::Softlight => {
+            if source <= 0.5 {
+                2.0 * backdrop * source + backdrop * backdrop * (1.0 - 2.0 * source)
+            } else {
+                2.0 * backdrop * (1.0 - source) + backdrop.sqrt() * (2.0 * source - 1.0)
+            }
+        }
+        BlendMode::Hardlight => {
+            if source <= 0.5 {
+                separable(BlendMode::Multiply, backdrop, 2.0 * source)
+            } else {
+                separable(BlendMode::Screen, backdrop, 2.0 * source - 1.0)
+            }
+        }
+        BlendMode::Vividlight => {
+            if source <= 0.5 {
+                separable(BlendMode::Colorburn, backdrop, 2.0 * source)
+            } else {
+                separable(BlendMode::Colordodge, backdrop, 2.0 * source - 1.0)
+            }
+        }
+        BlendMode::Linearlight => (backdrop + 2.0 * source - 1.0).clamp(0.0, 1.0),
+        BlendMode::Pinlight => {
+            if source <= 0.5 {
+                backdrop.min(2.0 * source)
+            } else {
+                backdrop.max(2.0 * source - 1.0)
+            }
+        }
+        // the tolerance keeps 8-bit pairs summing to exactly 255 on the bright side
+        BlendMode::Hardmix => {
+            if backdrop + source >= 1.0 - 1e-6 {
+                1.0
+            } else {
+                0.0
+            }
+        }
+        BlendMode::Difference => (backdrop - source).abs(),
+        BlendMode::Exclusion => backdrop + source - 2.0 * backdrop * source,
+        BlendMode::Subtract => (backdrop - source).max(0.0),
+        BlendMode::Divide => {
+            if source <= 0.0 {
+                if backdrop <= 0.0 {
+                    0.0
+                } else {
+                    1.0
+                }
+            } else {
+                (backdrop / source).min(1.0)
+            }
+        }
+        _ => source,
+    }
+}
+
+/// Blends the colors of two pixels with `mode`, with every component in `0.0..=1.0`.
+///
+/// Only the color is computed here, combining it with the alpha of both pixels is left to the caller.
+/// `Passthrough` and `Dissolve` return `source` unchanged.
+/// `Hue`, `Saturation`, `Color` and `Luminosity` recombine hue, saturation and luminance of the two pixels, with luminance weighted as Photoshop does.
+pub fn blend_f32(mode: BlendMode, backdrop: [f32; 3], source: [f32; 3]) -> [f32; 3] {
+    match mode {
+        // these compare the whole pixel and select one of them, not each channel
+        BlendMode::Darkercolor => {
+            if luminance(source) < luminance(backdrop) {
+                source
+            } else {
+                backdrop
+            }
+        }
+        BlendMode::Lightercolor => {
+            if luminance(source) > luminance(backdrop) {
+                source
+            } else {
+                backdrop
+            }
+        }
+        BlendMode::Hue => set_luminance(set_saturation(source, saturation(backdrop)), luminance(backdrop)),
+        BlendMode::Saturation => set_luminance(set_saturation(backdrop, saturation(source)), luminance(backdrop)),
+        BlendMode::Color => set_luminance(source, luminance(backdrop)),
+        BlendMode::Luminosity => set_luminance(backdrop, luminance(source)),
+        _ => {
+            let [br, bg, bb] = backdrop;
+            let [sr, sg, sb] = source;
+            [separable(mode, br, sr), separable(mode, bg, sg), separable(mode, bb, sb)]
+        }
+    }
+}
+
+/// 8-bit version of [`blend_f32`].
+pub fn blend(mode: BlendMode, backdrop: [u8; 3], source: [u8; 3]) -> [u8; 3] {
+    let to_f32 = |[r, g, b]: [u8; 3]| [r as f32 / 255.0, g as f32 / 255.0, b as f32 / 255.0];
+    let [r, g, b] = blend_f32(mode, to_f32(backdrop), to_f32(source));
+    let to_u8 = |value: f32| (value.clamp(0.0, 1.0) * 255.0).round() as u8;
+    [to_u8(r), to_u8(g), to_u8(b)]
+}
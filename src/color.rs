@@ -0,0 +1,54 @@
+//! Naive conversions between color spaces, without any color management.
+
+pub(crate) fn hsb_to_rgb(hue: f64, saturation: f64, brightness: f64) -> [f64; 3] {
+    let hue = hue.rem_euclid(360.0) / 60.0;
+    let chroma = brightness * saturation;
+    let x = chroma * (1.0 - (hue % 2.0 - 1.0).abs());
+    let [r, g, b] = match hue as u32 {
+        0 => [chroma, x, 0.0],
+        1 => [x, chroma, 0.0],
+        2 => [0.0, chroma, x],
+        3 => [0.0, x, chroma],
+        4 => [x, 0.0, chroma],
+        _ => [chroma, 0.0, x],
+    };
+    let m = brightness - chroma;
+    [r + m, g + m, b + m]
+}
+
+/// Components are ink coverage, 0.0 is no ink.
+pub(crate) fn cmyk_to_rgb(cyan: f64, magenta: f64, yellow: f64, black: f64) -> [f64; 3] {
+    [(1.0 - cyan) * (1.0 - black), (1.0 - magenta) * (1.0 - black), (1.0 - yellow) * (1.0 - black)]
+}
+
+/// CIE L\*a\*b\* (D50, as Photoshop uses) to sRGB.
+pub(crate) fn lab_to_rgb(l: f64, a: f64, b: f64) -> [f64; 3] {
+    fn f_inv(t: f64) -> f64 {
+        if t > 6.0 / 29.0 {
+            t * t * t
+        } else {
+            3.0 * (6.0 / 29.0) * (6.0 / 29.0) * (t - 4.0 / 29.0)
+        }
+    }
+    fn gamma(linear: f64) -> f64 {
+        let linear = linear.clamp(0.0, 1.0);
+        if linear <= 0.003_130_8 {
+            12.92 * linear
+        } else {
+            1.055 * linear.powf(1.0 / 2.4) - 0.055
+        }
+    }
+    let fy = (l + 16.0) / 116.0;
+    let x = 0.9642 * f_inv(fy + a / 500.0);
+    let y = f_inv(fy);
+    let z = 0.8249 * f_inv(fy - b / 200.0);
+    // sRGB matrix adapted to D50 with Bradford
+    let r = 3.133_856_1 * x - 1.616_866_7 * y - 0.490_614_6 * z;
+    let g = -0.978_768_4 * x + 1.916_141_5 * y + 0.033_454_0 * z;
+    let b = 0.071_945_3 * x - 0.228_991_4 * y + 1.405_242_7 * z;
+    [gamma(r), gamma(g), gamma(b)]
+}
+
+pub(crate) fn to_u8(value: f64) -> u8 {
+    (value.clamp(0.0, 1.0) * 255.0).round() as u8
+}
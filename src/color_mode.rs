@@ -6,26 +6,88 @@ use nom::number::complete::be_u32;
 use nom::IResult;
 
 use crate::header::{ColorMode, PsdHeader};
+use crate::owned_len;
 
-#[derive(Debug, PartialEq, Eq)]
-pub struct ColorModeData<'a>(Cow<'a, [u8]>);
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct ColorModeData<'a> {
+    color_mode: ColorMode,
+    #[cfg_attr(feature = "serde", serde(serialize_with = "crate::serialize::len"))]
+    data: Cow<'a, [u8]>,
+}
 
 impl<'a> ColorModeData<'a> {
     pub fn data(&self) -> &[u8] {
-        &self.0
+        &self.data
+    }
+    /// The palette of an indexed document as RGB triples. The data stores it planar, 256 reds followed by 256 greens and 256 blues.
+    /// `None` for other color modes, or unless the data is a 768-byte palette.
+    pub fn palette(&self) -> Option<[[u8; 3]; 256]> {
+        if self.color_mode != ColorMode::Indexed || self.data.len() != 768 {
+            return None;
+        }
+        let mut palette = [[0; 3]; 256];
+        for (index, color) in palette.iter_mut().enumerate() {
+            *color = [self.data[index], self.data[256 + index], self.data[512 + index]];
+        }
+        Some(palette)
+    }
+    /// The same as [`palette`](Self::palette).
+    #[deprecated(note = "use `palette`")]
+    pub fn indexed_palette_rgb(&self) -> Option<[[u8; 3]; 256]> {
+        self.palette()
+    }
+    /// The inks and curves of a duotone document as stored, `None` for other color modes.
+    pub fn duotone_raw(&self) -> Option<&[u8]> {
+        match self.color_mode {
+            ColorMode::Duotone => Some(&self.data),
+            _ => None,
+        }
+    }
+    /// The duotone specification of a duotone document, `None` for other color modes and when the data is too short to hold one.
+    pub fn duotone(&self) -> Option<DuotoneData<'_>> {
+        let data = self.duotone_raw()?;
+        let header = data.get(..4)?;
+        Some(DuotoneData { version: u16::from_be_bytes([header[0], header[1]]), ink_count: u16::from_be_bytes([header[2], header[3]]), data })
+    }
+    pub(crate) fn memory_footprint(&self) -> usize {
+        owned_len(&self.data)
     }
     pub(crate) fn into_static(self) -> ColorModeData<'static> {
-        let ColorModeData(data) = self;
-        ColorModeData(Cow::Owned(data.into_owned()))
+        let ColorModeData { color_mode, data } = self;
+        ColorModeData { color_mode, data: Cow::Owned(data.into_owned()) }
+    }
+}
+
+/// The duotone specification in the color mode data of a duotone document. Photoshop doesn't document its format, only the leading
+/// version and the number of inks are read, the same as in a Duotone Options (`.ado`) file. The inks, curves and overprint colors follow in [`data`](Self::data).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DuotoneData<'a> {
+    version: u16,
+    ink_count: u16,
+    data: &'a [u8],
+}
+
+impl<'a> DuotoneData<'a> {
+    pub fn version(&self) -> u16 {
+        self.version
+    }
+    /// 1 for a monotone, 2 for a duotone, 3 for a tritone and 4 for a quadtone.
+    pub fn ink_count(&self) -> u16 {
+        self.ink_count
+    }
+    /// The whole specification as stored, including the version and the ink count.
+    pub fn data(&self) -> &'a [u8] {
+        self.data
     }
 }
 
-pub(crate) fn parse_color_mode<'a, 'b>(input: &'a [u8], header: &'b PsdHeader) -> IResult<&'a [u8], ColorModeData<'a>> {
+pub(crate) fn parse_color_mode<'a>(input: &'a [u8], header: &PsdHeader) -> IResult<&'a [u8], ColorModeData<'a>> {
     let (input, len) = match header.color_mode() {
         ColorMode::Indexed => verify(be_u32, |len| *len == 768)(input)?,
         ColorMode::Duotone => be_u32(input)?,
         _ => verify(be_u32, |len| *len == 0)(input)?,
     };
     let (input, data) = take(len)(input)?;
-    Ok((input, ColorModeData(Cow::Borrowed(data))))
+    Ok((input, ColorModeData { color_mode: header.color_mode(), data: Cow::Borrowed(data) }))
 }
@@ -0,0 +1,279 @@
+//! Flattening the layers of a document into a single image.
+
+use crate::blend::blend_f32;
+use crate::error::PsdError;
+use crate::header::ColorMode;
+use crate::layer_info::{BlendMode, Clipping, LayerRecord, LayerTreeNode};
+use crate::rect::Rect;
+use crate::{plane_len, Psd};
+
+/// An 8-bit RGBA image with straight (not premultiplied) alpha, row by row.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RgbaImage {
+    width: u32,
+    height: u32,
+    data: Vec<u8>,
+}
+
+impl RgbaImage {
+    /// A fully transparent image.
+    pub fn new(width: u32, height: u32) -> Self {
+        RgbaImage { width, height, data: vec![0; width as usize * height as usize * 4] }
+    }
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+    /// `width * height * 4` bytes of `R, G, B, A`.
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+    pub fn into_raw(self) -> Vec<u8> {
+        self.data
+    }
+    pub fn pixel(&self, x: u32, y: u32) -> [u8; 4] {
+        let index = (y as usize * self.width as usize + x as usize) * 4;
+        [self.data[index], self.data[index + 1], self.data[index + 2], self.data[index + 3]]
+    }
+}
+
+/// Options for [`Psd::to_rgb8_with_options`] and [`Psd::to_rgba8_with_options`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CompositeOptions {
+    /// Composite the layers instead of using the image data when the version info (resource 1057) says the image data isn't the real merged image,
+    /// as for documents saved without "maximize compatibility". Only for documents [`Psd::composite`] supports, the others use the image data anyway.
+    pub prefer_layers_when_stale: bool,
+}
+
+impl Default for CompositeOptions {
+    fn default() -> Self {
+        CompositeOptions { prefer_layers_when_stale: true }
+    }
+}
+
+/// The area of the document being composited, in floating point RGBA with straight alpha.
+pub(crate) struct Canvas {
+    left: i32,
+    top: i32,
+    width: usize,
+    height: usize,
+    pixels: Vec<[f32; 4]>,
+}
+
+impl Canvas {
+    fn new(left: i32, top: i32, width: usize, height: usize) -> Self {
+        Canvas { left, top, width, height, pixels: vec![[0.0; 4]; width * height] }
+    }
+
+    fn empty_like(&self) -> Self {
+        Canvas::new(self.left, self.top, self.width, self.height)
+    }
+
+    /// Composites `source` over the pixel at `index`, `source` having straight alpha.
+    fn blend_pixel(&mut self, index: usize, mode: BlendMode, source: [f32; 4]) {
+        let [sr, sg, sb, sa] = source;
+        if sa <= 0.0 {
+            return;
+        }
+        let [br, bg, bb, ba] = self.pixels[index];
+        let [mr, mg, mb] = blend_f32(mode, [br, bg, bb], [sr, sg, sb]);
+        let alpha = sa + ba * (1.0 - sa);
+        let mix = |source: f32, backdrop: f32, mixed: f32| (sa * (1.0 - ba) * source + sa * ba * mixed + (1.0 - sa) * ba * backdrop) / alpha;
+        self.pixels[index] = [mix(sr, br, mr), mix(sg, bg, mg), mix(sb, bb, mb), alpha];
+    }
+
+    fn from_image(image: &RgbaImage, left: i32, top: i32) -> Self {
+        let pixels = image.data.chunks_exact(4).map(|pixel| [0, 1, 2, 3].map(|channel| pixel[channel] as f32 / 255.0)).collect();
+        Canvas { left, top, width: image.width as usize, height: image.height as usize, pixels }
+    }
+
+    fn into_image(self) -> RgbaImage {
+        let to_u8 = |value: f32| (value.clamp(0.0, 1.0) * 255.0).round() as u8;
+        RgbaImage {
+            width: self.width as u32,
+            height: self.height as u32,
+            data: self.pixels.iter().flat_map(|&[r, g, b, a]| [to_u8(r), to_u8(g), to_u8(b), to_u8(a)]).collect(),
+        }
+    }
+}
+
+/// Coverage of the user mask at a document position, `1.0` where the layer shows.
+fn mask_value(record: &LayerRecord, x: i32, y: i32) -> Result<f32, PsdError> {
+    let (mask, channel) = match (record.layer_mask_data(), record.user_supplied_layer_mask()) {
+        (Some(mask), Some(channel)) if !mask.disabled() => (mask, channel),
+        _ => return Ok(1.0),
+    };
+    let rect = mask.canvas_rect(record);
+    let value = if rect.contains(x, y) {
+        let index = (y - rect.top()) as usize * rect.width() as usize + (x - rect.left()) as usize;
+        channel.try_raw_data()?.get(index).copied().unwrap_or(mask.default_color())
+    } else {
+        mask.default_color()
+    };
+    let value = if mask.inverted() { 255 - value } else { value };
+    let value = value as f32 / 255.0;
+    let density = mask.user_mask_adjustments().map_or(1.0, |adjustments| adjustments.density() as f32);
+    Ok(1.0 - density * (1.0 - value))
+}
+
+/// Draws the pixels of a layer (not a group) into `canvas` with `mode`.
+/// `clip` is the alpha of the clipping base below, applied when the layer is clipped.
+/// When `coverage` is given, it receives the alpha of the layer at each canvas pixel, for clipping the layers above it.
+fn draw_layer(canvas: &mut Canvas, record: &LayerRecord, mode: BlendMode, opacity: f32, clip: Option<&[f32]>, mut coverage: Option<&mut Vec<f32>>) -> Result<(), PsdError> {
+    if let Some(coverage) = coverage.as_deref_mut() {
+        coverage.iter_mut().for_each(|value| *value = 0.0);
+    }
+    let channels = record.color_channels().take(3).map(|channel| channel.try_raw_data()).collect::<Result<Vec<_>, _>>()?;
+    if channels.len() < 3 {
+        return Ok(());
+    }
+    let alpha = record.transparency_mask().map(|channel| channel.try_raw_data()).transpose()?;
+    let width = (record.layer_right() - record.layer_left()).max(0) as usize;
+    let top = record.layer_top().max(canvas.top);
+    let bottom = record.layer_bottom().min(canvas.top + canvas.height as i32);
+    let left = record.layer_left().max(canvas.left);
+    let right = record.layer_right().min(canvas.left + canvas.width as i32);
+    for y in top..bottom {
+        for x in left..right {
+            let source = (y - record.layer_top()) as usize * width + (x - record.layer_left()) as usize;
+            let index = (y - canvas.top) as usize * canvas.width + (x - canvas.left) as usize;
+            let sample = |channel: &[u8]| channel.get(source).map_or(0.0, |&value| value as f32 / 255.0);
+            let mut a = alpha.map_or(1.0, sample) * mask_value(record, x, y)?;
+            if let Some(coverage) = coverage.as_deref_mut() {
+                coverage[index] = a;
+            }
+            if let Some(clip) = clip {
+                a *= clip[index];
+            }
+            canvas.blend_pixel(index, mode, [sample(channels[0]), sample(channels[1]), sample(channels[2]), a * opacity]);
+        }
+    }
+    Ok(())
+}
+
+/// Composites `nodes` (top-most first, as in the layer tree) onto `canvas`.
+fn composite_nodes(canvas: &mut Canvas, nodes: &[LayerTreeNode], opacity: f32) -> Result<(), PsdError> {
+    // the alpha of the current clipping base, which clipped layers above it are limited to
+    let mut base_coverage = vec![0.0; canvas.pixels.len()];
+    let mut base_visible = false;
+    for node in nodes.iter().rev() {
+        let record = match node {
+            LayerTreeNode::Leaf(record) | LayerTreeNode::Node { folder: record, .. } => record,
+        };
+        let clipped = record.clipping() == Clipping::NonBase;
+        if !clipped {
+            base_visible = record.is_visible();
+        }
+        if !record.is_visible() || clipped && !base_visible {
+            continue;
+        }
+        let layer_opacity = opacity * record.opacity() as f32 / 255.0;
+        match node {
+            LayerTreeNode::Leaf(record) if clipped => draw_layer(canvas, record, record.blend_mode(), layer_opacity, Some(&base_coverage), None)?,
+            LayerTreeNode::Leaf(record) => draw_layer(canvas, record, record.blend_mode(), layer_opacity, None, Some(&mut base_coverage))?,
+            LayerTreeNode::Node { folder, children } => {
+                let mode = folder.group_blend_mode().unwrap_or_else(|| folder.blend_mode());
+                if mode == BlendMode::Passthrough && !clipped {
+                    composite_nodes(canvas, children, layer_opacity)?;
+                    base_coverage.iter_mut().for_each(|value| *value = 1.0);
+                    continue;
+                }
+                let mut group = canvas.empty_like();
+                composite_nodes(&mut group, children, 1.0)?;
+                let mode = if mode == BlendMode::Passthrough { BlendMode::Normal } else { mode };
+                for (index, pixel) in group.pixels.iter().enumerate() {
+                    let x = canvas.left + (index % canvas.width) as i32;
+                    let y = canvas.top + (index / canvas.width) as i32;
+                    let mut a = pixel[3] * mask_value(folder, x, y)?;
+                    if clipped {
+                        a *= base_coverage[index];
+                    } else {
+                        base_coverage[index] = a;
+                    }
+                    canvas.blend_pixel(index, mode, [pixel[0], pixel[1], pixel[2], a * layer_opacity]);
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+impl<'a> LayerRecord<'a> {
+    /// Draws the pixels of this layer over `canvas` with the normal blend mode, honoring the layer opacity and user mask but not the blend mode or visibility.
+    ///
+    /// `canvas_rect` is the area of the document the canvas covers, so the layer lands at its document position; it must have the size of the canvas.
+    /// Only layers of 8-bit RGB documents can be drawn, others are left out when they have fewer than three color channels.
+    pub fn rasterize_into(&self, canvas: &mut RgbaImage, canvas_rect: Rect) -> Result<(), PsdError> {
+        if (canvas_rect.width(), canvas_rect.height()) != (canvas.width(), canvas.height()) {
+            return Err(PsdError::CanvasSizeMismatch { canvas: (canvas.width(), canvas.height()), rect: (canvas_rect.width(), canvas_rect.height()) });
+        }
+        if let Some(channel) = self.channel_info().iter().find(|channel| channel.depth() != 8) {
+            return Err(PsdError::Unsupported(format!("drawing {}-bit layers", channel.depth())));
+        }
+        let mut target = Canvas::from_image(canvas, canvas_rect.left(), canvas_rect.top());
+        draw_layer(&mut target, self, BlendMode::Normal, self.opacity() as f32 / 255.0, None, None)?;
+        *canvas = target.into_image();
+        Ok(())
+    }
+}
+
+impl<'a> Psd<'a> {
+    /// Flattens the visible layers into an image the size of the document.
+    ///
+    /// Only 8-bit RGB documents are supported. Layers are combined with their blend mode, opacity, clipping and user mask;
+    /// pass-through groups are applied layer by layer, scaled by the group opacity, rather than exactly as Photoshop does.
+    /// Layer effects, adjustment layers, fill opacity and vector masks are not rendered. Neither is knockout, so the
+    /// [transparency shapes layer](crate::layer_info::AdditionalLayerInformation::TransparencyShapesLayer) flag, which only decides how far a knockout reaches, makes no difference here.
+    ///
+    /// Fails when the channels of a layer that is drawn can't be decompressed.
+    pub fn composite(&self) -> Result<RgbaImage, PsdError> {
+        let header = self.header();
+        self.composite_region(Rect::from_size(0, 0, header.width(), header.height()))
+    }
+
+    /// Flattens the visible layers like [`composite`](Self::composite), but only the pixels inside `region`.
+    ///
+    /// The image has the size of `region` cropped to the document, empty when they don't overlap, and its top left pixel is the
+    /// top left corner of the cropped region. Layers are only rasterized where they overlap it.
+    pub fn composite_region(&self, region: Rect) -> Result<RgbaImage, PsdError> {
+        let header = self.header();
+        if header.color_mode() != ColorMode::RGB || header.depth() != 8 {
+            return Err(PsdError::Unsupported(format!("compositing {:?} {}-bit documents", header.color_mode(), header.depth())));
+        }
+        let region = match Rect::from_size(0, 0, header.width(), header.height()).intersection(&region) {
+            Some(region) => region,
+            None => return Ok(RgbaImage::new(0, 0)),
+        };
+        // the canvas holds 4 floats for each pixel
+        plane_len(region.width(), region.height(), 8, 16)?;
+        let mut canvas = Canvas::new(region.left(), region.top(), region.width() as usize, region.height() as usize);
+        composite_nodes(&mut canvas, self.layer_information().layer_info(), 1.0)?;
+        Ok(canvas.into_image())
+    }
+
+    /// Composites the layers and compares the result with the stored merged image, allowing each channel to differ by `tolerance`.
+    ///
+    /// Photoshop stores the merged colors matted against white, so the composite is matted the same way before comparing.
+    /// The alpha is compared too when the merged image has a transparency channel.
+    pub fn composite_matches_stored(&self, tolerance: u8) -> Result<bool, PsdError> {
+        let composite = self.composite()?;
+        let stored = self.image_data().try_raw_data()?;
+        if stored.len() < 3 {
+            return Err(PsdError::MissingChannel(stored.len()));
+        }
+        let stored_alpha = if self.layer_information().merged_transparency() { stored.get(3) } else { None };
+        let close = |a: u8, b: u8| a.abs_diff(b) <= tolerance;
+        Ok(composite.data().chunks_exact(4).enumerate().all(|(index, pixel)| {
+            let stored_value = |channel: usize| stored[channel].get(index).copied().unwrap_or(0);
+            (0..3).all(|channel| close(matte_white(pixel[channel], pixel[3]), stored_value(channel))) && stored_alpha.is_none_or(|_| close(pixel[3], stored_value(3)))
+        }))
+    }
+}
+
+/// `value` with the straight `alpha` composited over white, the way Photoshop stores the merged colors.
+pub(crate) fn matte_white(value: u8, alpha: u8) -> u8 {
+    let alpha = alpha as u32;
+    ((value as u32 * alpha + 255 * (255 - alpha) + 127) / 255) as u8
+}
@@ -0,0 +1,228 @@
+use crate::color::{cmyk_to_rgb, lab_to_rgb, to_u8};
+use crate::composite::{matte_white, CompositeOptions, RgbaImage};
+use crate::error::PsdError;
+use crate::header::{ColorMode, PsdHeader};
+use crate::layer_info::LayerRecord;
+use crate::rect::Rect;
+use crate::{plane_len, Psd};
+
+impl<'a> Psd<'a> {
+    /// The merged image as interleaved 8-bit sRGB, `width * height * 3` bytes. Channels past the color channels (alpha, spot colors) are ignored.
+    ///
+    /// With the `color-management` feature enabled, grayscale, RGB and CMYK documents carrying an ICC profile (resource 1039) and Lab documents
+    /// are converted with lcms2. Otherwise the device values are converted with naive formulas, which is exact for RGB but only an approximation for the others.
+    ///
+    /// Duotone documents are only approximated as well: their single channel is rendered as grayscale, ignoring the inks and curves of the color mode data,
+    /// whose format isn't documented.
+    ///
+    /// When the version info says the image data isn't the real merged image, the layers are composited instead and matted against white,
+    /// see [`CompositeOptions::prefer_layers_when_stale`].
+    pub fn to_rgb8(&self) -> Result<Vec<u8>, PsdError> {
+        self.to_rgb8_with_options(&CompositeOptions::default())
+    }
+
+    /// [`to_rgb8`](Self::to_rgb8) with `options` deciding whether a composite of the layers replaces stale image data.
+    pub fn to_rgb8_with_options(&self, options: &CompositeOptions) -> Result<Vec<u8>, PsdError> {
+        if let Some(composite) = self.stale_composite(options) {
+            return Ok(composite.data().chunks_exact(4).flat_map(|pixel| [matte_white(pixel[0], pixel[3]), matte_white(pixel[1], pixel[3]), matte_white(pixel[2], pixel[3])]).collect());
+        }
+        let header = self.header();
+        if header.depth() != 8 && header.color_mode() != ColorMode::Bitmap {
+            return Err(PsdError::Unsupported(format!("converting {}-bit image data", header.depth())));
+        }
+        // fails for zip compressed data that can't be decompressed, before anything goes through `raw_data`
+        self.image_data().try_raw_data()?;
+        // and for documents whose RGBA pixels wouldn't fit in memory, so that the sizes below can't wrap
+        plane_len(header.width(), header.height(), 8, 4)?;
+        #[cfg(feature = "color-management")]
+        if let Some(rgb) = self.to_rgb8_color_managed()? {
+            return Ok(rgb);
+        }
+        self.to_rgb8_naive()
+    }
+
+    /// The merged image like [`to_rgb8`](Self::to_rgb8) with an alpha, `width * height * 4` bytes.
+    ///
+    /// The alpha comes from the `Alph` block ([`LayerAndMaskInformation::merged_alpha`](crate::layer_info::LayerAndMaskInformation::merged_alpha)) when the document has one,
+    /// otherwise from the channel after the color channels when the layer count marks it as the transparency of the merged image. Without either the image is opaque.
+    /// A composite of the layers replacing stale image data keeps its own alpha.
+    ///
+    /// Fails like [`to_rgb8`](Self::to_rgb8), and when the `Alph` block can't be decompressed.
+    pub fn to_rgba8(&self) -> Result<Vec<u8>, PsdError> {
+        self.to_rgba8_with_options(&CompositeOptions::default())
+    }
+
+    /// [`to_rgba8`](Self::to_rgba8) with `options`, see [`to_rgb8_with_options`](Self::to_rgb8_with_options).
+    pub fn to_rgba8_with_options(&self, options: &CompositeOptions) -> Result<Vec<u8>, PsdError> {
+        if let Some(composite) = self.stale_composite(options) {
+            return Ok(composite.into_raw());
+        }
+        let rgb = self.to_rgb8_with_options(&CompositeOptions { prefer_layers_when_stale: false })?;
+        let color_channels = match self.header().color_mode() {
+            ColorMode::RGB | ColorMode::Lab => 3,
+            ColorMode::CMYK => 4,
+            _ => 1,
+        };
+        let information = self.layer_information();
+        let alpha = match information.try_merged_alpha()? {
+            Some(alpha) => Some(alpha),
+            None if information.merged_transparency() => self.image_data().raw_data().get(color_channels).map(|alpha| &alpha[..]),
+            None => None,
+        };
+        Ok(rgb.chunks_exact(3).enumerate().flat_map(|(index, pixel)| [pixel[0], pixel[1], pixel[2], alpha.and_then(|alpha| alpha.get(index).copied()).unwrap_or(255)]).collect())
+    }
+
+    /// The merged image from [`to_rgba8`](Self::to_rgba8) as an [`image::RgbaImage`] of the size of the document,
+    /// the alpha coming from the merged alpha channel when there is one. This is the image stored in the file, not a composite of the layers,
+    /// unless the version info marks it as stale.
+    #[cfg(feature = "image")]
+    pub fn composite_image(&self) -> Result<image::RgbaImage, PsdError> {
+        let header = self.header();
+        let rgba = self.to_rgba8()?;
+        Ok(image::RgbaImage::from_raw(header.width(), header.height(), rgba).expect("to_rgba8 gives 4 bytes for each pixel"))
+    }
+
+    /// The merged image of an 8-bit Lab document as `[L*, a*, b*]`, with L\* in `0.0..=100.0` and a\*, b\* in `-128.0..=127.0`.
+    /// `None` for other color modes and depths.
+    pub fn lab_pixels(&self) -> Option<Vec<[f32; 3]>> {
+        let header = self.header();
+        if header.color_mode() != ColorMode::Lab || header.depth() != 8 {
+            return None;
+        }
+        let planes = self.image_data().try_raw_data().ok()?;
+        let (l, a, b) = (planes.first()?, planes.get(1)?, planes.get(2)?);
+        Some(l.iter().zip(a.iter()).zip(b.iter()).map(|((&l, &a), &b)| [l as f32 * 100.0 / 255.0, a as f32 - 128.0, b as f32 - 128.0]).collect())
+    }
+
+    /// The composite of the layers when `options` prefer it and the version info marks the image data as not the real merged image.
+    /// `None` when either doesn't hold, the document has no layers, or [`composite`](Self::composite) fails, so that the image data is used instead.
+    fn stale_composite(&self, options: &CompositeOptions) -> Option<RgbaImage> {
+        let stale = self.image_resources().version_info().is_some_and(|info| !info.has_real_merged_data());
+        if !options.prefer_layers_when_stale || !stale || self.layer_information().layer_info().is_empty() {
+            return None;
+        }
+        self.composite().ok()
+    }
+
+    fn to_rgb8_naive(&self) -> Result<Vec<u8>, PsdError> {
+        let header = self.header();
+        let (width, height) = (header.width() as usize, header.height() as usize);
+        let planes = self.image_data().raw_data();
+        let plane = |index: usize| planes.get(index).map(|plane| &plane[..]).ok_or(PsdError::MissingChannel(index));
+        let mut rgb = Vec::with_capacity(width * height * 3);
+        match header.color_mode() {
+            ColorMode::Bitmap => {
+                // one bit per pixel, rows padded to whole bytes, a set bit is black
+                let bits = plane(0)?;
+                let row_bytes = width.div_ceil(8);
+                for y in 0..height {
+                    for x in 0..width {
+                        let black = bits[y * row_bytes + x / 8] & (0x80 >> (x % 8)) != 0;
+                        rgb.extend_from_slice(&[if black { 0 } else { 255 }; 3]);
+                    }
+                }
+            }
+            // the ink curves aren't applied, so a duotone shows as the gray the channel stores
+            ColorMode::Grayscale | ColorMode::Duotone => rgb.extend(plane(0)?.iter().flat_map(|&gray| [gray; 3])),
+            ColorMode::Indexed => {
+                let palette = self.color_mode().palette().ok_or_else(|| PsdError::Unsupported("an indexed document without a 768-byte palette".to_string()))?;
+                rgb.extend(plane(0)?.iter().flat_map(|&index| palette[index as usize]));
+            }
+            ColorMode::RGB => {
+                let (r, g, b) = (plane(0)?, plane(1)?, plane(2)?);
+                rgb.extend((0..width * height).flat_map(|i| [r[i], g[i], b[i]]));
+            }
+            ColorMode::CMYK => {
+                // stored inverted, 255 is no ink
+                let (c, m, y, k) = (plane(0)?, plane(1)?, plane(2)?, plane(3)?);
+                let ink = |value: u8| 1.0 - value as f64 / 255.0;
+                rgb.extend((0..width * height).flat_map(|i| cmyk_to_rgb(ink(c[i]), ink(m[i]), ink(y[i]), ink(k[i])).map(to_u8)));
+            }
+            ColorMode::Lab => {
+                let lab = self.lab_pixels().ok_or(PsdError::MissingChannel(planes.len()))?;
+                rgb.extend(lab.into_iter().flat_map(|[l, a, b]| lab_to_rgb(l as f64, a as f64, b as f64).map(to_u8)));
+            }
+            mode @ ColorMode::Multichannel => return Err(PsdError::Unsupported(format!("converting {:?} to RGB", mode))),
+        }
+        Ok(rgb)
+    }
+
+    /// Returns `None` when the document doesn't need (or can't use) a color managed conversion.
+    #[cfg(feature = "color-management")]
+    fn to_rgb8_color_managed(&self) -> Result<Option<Vec<u8>>, PsdError> {
+        use lcms2::{CIExyY, GlobalContext, Intent, PixelFormat, Profile, Transform};
+
+        let header = self.header();
+        let pixels = header.width() as usize * header.height() as usize;
+        let (format, channels) = match header.color_mode() {
+            ColorMode::Grayscale => (PixelFormat::GRAY_8, 1),
+            ColorMode::RGB => (PixelFormat::RGB_8, 3),
+            // the "reversed" layout matches Photoshop's inverted ink values
+            ColorMode::CMYK => (PixelFormat::CMYK_8_REV, 4),
+            ColorMode::Lab => (PixelFormat::Lab_8, 3),
+            _ => return Ok(None),
+        };
+        let profile = match (header.color_mode(), self.image_resources().find(1039)) {
+            // Photoshop's Lab is relative to D50
+            (ColorMode::Lab, _) => Profile::new_lab2_context(GlobalContext::new(), &CIExyY { x: 0.3457, y: 0.3585, Y: 1.0 })?,
+            (_, Some(icc)) => Profile::new_icc(icc.resource_data())?,
+            (_, None) => return Ok(None),
+        };
+        let planes = self.image_data().raw_data();
+        if planes.len() < channels {
+            return Err(PsdError::MissingChannel(planes.len()));
+        }
+        let mut device = Vec::with_capacity(pixels * channels);
+        device.extend((0..pixels).flat_map(|i| planes[..channels].iter().map(move |plane| plane[i])));
+        let mut rgb = vec![0; pixels * 3];
+        let transform = Transform::<u8, u8>::new(&profile, format, &Profile::new_srgb(), PixelFormat::RGB_8, Intent::Perceptual)?;
+        transform.transform_pixels(&device, &mut rgb);
+        Ok(Some(rgb))
+    }
+}
+
+impl<'a> LayerRecord<'a> {
+    /// The pixels of this layer on their own as interleaved 8-bit `R, G, B, A`, with the width and height of the layer bounds.
+    /// The alpha is the transparency mask (-1), the layer being opaque without one, and neither the opacity nor the masks are applied.
+    ///
+    /// `header` is the one of the document the layer comes from. Only 8-bit grayscale, duotone, RGB and CMYK documents are supported,
+    /// CMYK converted with the naive formula of [`Psd::to_rgb8`]. `None` for other documents, for a layer with an empty area,
+    /// and when a channel is missing or can't be decoded.
+    pub fn to_rgba8(&self, header: &PsdHeader) -> Option<(u32, u32, Vec<u8>)> {
+        let bounds = Rect::new(self.layer_top(), self.layer_left(), self.layer_bottom(), self.layer_right());
+        let (width, height) = (bounds.width(), bounds.height());
+        if bounds.is_empty() || header.depth() != 8 {
+            return None;
+        }
+        let color_channels = match header.color_mode() {
+            ColorMode::Grayscale | ColorMode::Duotone => 1,
+            ColorMode::RGB => 3,
+            ColorMode::CMYK => 4,
+            _ => return None,
+        };
+        let pixels = plane_len(width, height, 8, 4).ok()? / 4;
+        let planes = self.color_channels().take(color_channels).map(|channel| channel.try_raw_data().ok().filter(|plane| plane.len() >= pixels)).collect::<Option<Vec<_>>>()?;
+        if planes.len() < color_channels {
+            return None;
+        }
+        let alpha = match self.transparency_mask() {
+            Some(channel) => Some(channel.try_raw_data().ok().filter(|alpha| alpha.len() >= pixels)?),
+            None => None,
+        };
+        let mut rgba = Vec::with_capacity(pixels * 4);
+        for i in 0..pixels {
+            let [r, g, b] = match &planes[..] {
+                [gray] => [gray[i]; 3],
+                [r, g, b] => [r[i], g[i], b[i]],
+                [c, m, y, k] => {
+                    // stored inverted, 255 is no ink
+                    let ink = |value: u8| 1.0 - value as f64 / 255.0;
+                    cmyk_to_rgb(ink(c[i]), ink(m[i]), ink(y[i]), ink(k[i])).map(to_u8)
+                }
+                _ => unreachable!("one, three or four color channels"),
+            };
+            rgba.extend_from_slice(&[r, g, b, alpha.map_or(255, |alpha| alpha[i])]);
+        }
+        Some((width, height, rgba))
+    }
+}
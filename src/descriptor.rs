@@ -0,0 +1,357 @@
+use std::borrow::Cow;
+
+use nom::bytes::complete::take;
+use nom::combinator::map;
+use nom::multi::count;
+use nom::error::{Error, ErrorKind};
+use nom::number::complete::{be_f64, be_i32, be_i64, be_u32, be_u8};
+use nom::IResult;
+
+use crate::strings::parse_unicode_string;
+
+/// An action descriptor, the generic key/value structure a lot of additional layer information and image resources are stored in.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Descriptor<'a> {
+    name: String,
+    class_id: String,
+    items: Vec<(String, DescriptorValue<'a>)>,
+}
+
+impl<'a> Descriptor<'a> {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+    pub fn class_id(&self) -> &str {
+        &self.class_id
+    }
+    pub fn items(&self) -> &[(String, DescriptorValue<'a>)] {
+        &self.items
+    }
+    pub fn get(&self, key: &str) -> Option<&DescriptorValue<'a>> {
+        self.items.iter().find(|(k, _)| k == key).map(|(_, value)| value)
+    }
+    pub(crate) fn into_items(self) -> Vec<(String, DescriptorValue<'a>)> {
+        self.items
+    }
+    pub(crate) fn into_static(self) -> Descriptor<'static> {
+        let Descriptor { name, class_id, items } = self;
+        Descriptor { name, class_id, items: items.into_iter().map(|(key, value)| (key, value.into_static())).collect() }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum FloatUnit {
+    Angle,
+    Density,
+    Distance,
+    None,
+    Percent,
+    Pixels,
+    Points,
+    Millimeters,
+    Unknown(#[cfg_attr(feature = "serde", serde(serialize_with = "crate::serialize::text"))] [u8; 4]),
+}
+
+impl FloatUnit {
+    fn from_bytes(value: [u8; 4]) -> Self {
+        match &value {
+            b"#Ang" => FloatUnit::Angle,
+            b"#Rsl" => FloatUnit::Density,
+            b"#Rlt" => FloatUnit::Distance,
+            b"#Nne" => FloatUnit::None,
+            b"#Prc" => FloatUnit::Percent,
+            b"#Pxl" => FloatUnit::Pixels,
+            b"#Pnt" => FloatUnit::Points,
+            b"#Mlm" => FloatUnit::Millimeters,
+            _ => FloatUnit::Unknown(value),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum ReferenceItem {
+    Property { class_name: String, class_id: String, key_id: String },
+    Class { class_name: String, class_id: String },
+    Enumerated { class_name: String, class_id: String, type_id: String, value: String },
+    Offset { class_name: String, class_id: String, offset: u32 },
+    Identifier(u32),
+    Index(u32),
+    Name { class_name: String, class_id: String, name: String },
+}
+
+/// A value of a descriptor item, named after its OSType.
+///
+/// `GlbO` and `GlbC` are read as `Objc` and `type`, and `Pth ` is kept as [`Raw`](Self::Raw). Any other OSType is read the way the types
+/// holding bytes are laid out, a 4-byte length then the data, and kept as `Raw` too. The only ones that fail the descriptor are those whose
+/// length runs past the end of the data, which hold something other than a length there so that the value can't be skipped.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum DescriptorValue<'a> {
+    Reference(Vec<ReferenceItem>),
+    Descriptor(Descriptor<'a>),
+    List(Vec<DescriptorValue<'a>>),
+    Double(f64),
+    UnitFloat { unit: FloatUnit, value: f64 },
+    String(String),
+    Enumerated { type_id: String, value: String },
+    Integer(i32),
+    LargeInteger(i64),
+    Boolean(bool),
+    Class { class_name: String, class_id: String },
+    Alias(#[cfg_attr(feature = "serde", serde(serialize_with = "crate::serialize::len"))] Cow<'a, [u8]>),
+    RawData(#[cfg_attr(feature = "serde", serde(serialize_with = "crate::serialize::len"))] Cow<'a, [u8]>),
+    /// `ObAr`, an object array: a descriptor whose items hold one value for each of the `count` objects, usually as [`UnitFloats`](Self::UnitFloats).
+    ObjectArray { count: u32, descriptor: Descriptor<'a> },
+    /// `UnFl`, several floats of the same unit.
+    UnitFloats { unit: FloatUnit, values: Vec<f64> },
+    /// A value with a length but no interpretation here, such as a `Pth ` file path or an OSType this doesn't know.
+    Raw {
+        #[cfg_attr(feature = "serde", serde(serialize_with = "crate::serialize::text"))]
+        os_type: [u8; 4],
+        #[cfg_attr(feature = "serde", serde(serialize_with = "crate::serialize::len"))]
+        data: Cow<'a, [u8]>,
+    },
+}
+
+impl<'a> DescriptorValue<'a> {
+    pub(crate) fn into_static(self) -> DescriptorValue<'static> {
+        match self {
+            DescriptorValue::Reference(items) => DescriptorValue::Reference(items),
+            DescriptorValue::Descriptor(descriptor) => DescriptorValue::Descriptor(descriptor.into_static()),
+            DescriptorValue::List(list) => DescriptorValue::List(list.into_iter().map(DescriptorValue::into_static).collect()),
+            DescriptorValue::Double(value) => DescriptorValue::Double(value),
+            DescriptorValue::UnitFloat { unit, value } => DescriptorValue::UnitFloat { unit, value },
+            DescriptorValue::String(value) => DescriptorValue::String(value),
+            DescriptorValue::Enumerated { type_id, value } => DescriptorValue::Enumerated { type_id, value },
+            DescriptorValue::Integer(value) => DescriptorValue::Integer(value),
+            DescriptorValue::LargeInteger(value) => DescriptorValue::LargeInteger(value),
+            DescriptorValue::Boolean(value) => DescriptorValue::Boolean(value),
+            DescriptorValue::Class { class_name, class_id } => DescriptorValue::Class { class_name, class_id },
+            DescriptorValue::Alias(data) => DescriptorValue::Alias(Cow::Owned(data.into_owned())),
+            DescriptorValue::RawData(data) => DescriptorValue::RawData(Cow::Owned(data.into_owned())),
+            DescriptorValue::ObjectArray { count, descriptor } => DescriptorValue::ObjectArray { count, descriptor: descriptor.into_static() },
+            DescriptorValue::UnitFloats { unit, values } => DescriptorValue::UnitFloats { unit, values },
+            DescriptorValue::Raw { os_type, data } => DescriptorValue::Raw { os_type, data: Cow::Owned(data.into_owned()) },
+        }
+    }
+    /// The value of `Double`, `UnitFloat`, `Integer` and `LargeInteger` as `f64`.
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            DescriptorValue::Double(value) | DescriptorValue::UnitFloat { value, .. } => Some(*value),
+            DescriptorValue::Integer(value) => Some(*value as f64),
+            DescriptorValue::LargeInteger(value) => Some(*value as f64),
+            _ => None,
+        }
+    }
+    pub fn as_i64(&self) -> Option<i64> {
+        match self {
+            DescriptorValue::Integer(value) => Some(*value as i64),
+            DescriptorValue::LargeInteger(value) => Some(*value),
+            _ => None,
+        }
+    }
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            DescriptorValue::Boolean(value) => Some(*value),
+            _ => None,
+        }
+    }
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            DescriptorValue::String(value) => Some(value),
+            _ => None,
+        }
+    }
+    /// The enumeration value of `Enumerated`.
+    pub fn as_enum(&self) -> Option<&str> {
+        match self {
+            DescriptorValue::Enumerated { value, .. } => Some(value),
+            _ => None,
+        }
+    }
+    pub fn as_descriptor(&self) -> Option<&Descriptor<'a>> {
+        match self {
+            DescriptorValue::Descriptor(descriptor) => Some(descriptor),
+            _ => None,
+        }
+    }
+    pub fn as_list(&self) -> Option<&[DescriptorValue<'a>]> {
+        match self {
+            DescriptorValue::List(list) => Some(list),
+            _ => None,
+        }
+    }
+    pub fn as_bytes(&self) -> Option<&[u8]> {
+        match self {
+            DescriptorValue::Alias(data) | DescriptorValue::RawData(data) | DescriptorValue::Raw { data, .. } => Some(data),
+            _ => None,
+        }
+    }
+}
+
+/// Parses the 4-byte descriptor version (always 16) followed by a descriptor, the layout used inside additional layer information.
+/// Descriptors and lists nested more than 64 deep fail.
+pub(crate) fn parse_versioned_descriptor(input: &[u8]) -> IResult<&[u8], Descriptor<'_>> {
+    let (input, version) = be_u32(input)?;
+    if version != 16 {
+        return Err(nom::Err::Error(Error::new(input, ErrorKind::Verify)));
+    }
+    parse_descriptor(input, 0)
+}
+
+/// How deep descriptors and lists can nest, past which the data fails to parse rather than overflowing the stack.
+const MAX_DEPTH: usize = 64;
+
+/// Fails for a descriptor or a list inside `depth` others that would go past [`MAX_DEPTH`].
+fn check_depth(input: &[u8], depth: usize) -> IResult<&[u8], ()> {
+    if depth == MAX_DEPTH {
+        return Err(nom::Err::Error(Error::new(input, ErrorKind::TooLarge)));
+    }
+    Ok((input, ()))
+}
+
+/// Parses a descriptor inside `depth` descriptors and lists.
+fn parse_descriptor(input: &[u8], depth: usize) -> IResult<&[u8], Descriptor<'_>> {
+    let (input, ()) = check_depth(input, depth)?;
+    let (input, name) = parse_unicode_string(input)?;
+    let (input, class_id) = parse_id(input)?;
+    let (mut input, count) = be_u32(input)?;
+    let mut items = Vec::new();
+    for _ in 0..count {
+        let (i, key) = parse_id(input)?;
+        let (i, value) = parse_value(i, depth + 1)?;
+        items.push((key, value));
+        input = i;
+    }
+    Ok((input, Descriptor { name, class_id, items }))
+}
+
+/// A class/key id is either a 4-byte code (when the length is 0) or a string of the given length.
+fn parse_id(input: &[u8]) -> IResult<&[u8], String> {
+    let (input, len) = be_u32(input)?;
+    let len = if len == 0 { 4 } else { len };
+    map(take(len), |id| String::from_utf8_lossy(id).into_owned())(input)
+}
+
+fn parse_os_type(input: &[u8]) -> IResult<&[u8], [u8; 4]> {
+    let (input, os_type) = take(4usize)(input)?;
+    Ok((input, [os_type[0], os_type[1], os_type[2], os_type[3]]))
+}
+
+/// Parses a value inside `depth` descriptors and lists.
+fn parse_value(input: &[u8], depth: usize) -> IResult<&[u8], DescriptorValue<'_>> {
+    let (input, os_type) = parse_os_type(input)?;
+    parse_value_of_type(input, os_type, depth)
+}
+
+fn parse_value_of_type(input: &[u8], os_type: [u8; 4], depth: usize) -> IResult<&[u8], DescriptorValue<'_>> {
+    match &os_type {
+        b"obj " => {
+            let (mut input, count) = be_u32(input)?;
+            let mut items = Vec::new();
+            for _ in 0..count {
+                let (i, item) = parse_reference_item(input)?;
+                items.push(item);
+                input = i;
+            }
+            Ok((input, DescriptorValue::Reference(items)))
+        }
+        b"Objc" | b"GlbO" => map(|input| parse_descriptor(input, depth), DescriptorValue::Descriptor)(input),
+        b"VlLs" => {
+            let (input, ()) = check_depth(input, depth)?;
+            let (mut input, count) = be_u32(input)?;
+            let mut list = Vec::new();
+            for _ in 0..count {
+                let (i, value) = parse_value(input, depth + 1)?;
+                list.push(value);
+                input = i;
+            }
+            Ok((input, DescriptorValue::List(list)))
+        }
+        b"doub" => map(be_f64, DescriptorValue::Double)(input),
+        b"UntF" => {
+            let (input, unit) = parse_os_type(input)?;
+            let (input, value) = be_f64(input)?;
+            Ok((input, DescriptorValue::UnitFloat { unit: FloatUnit::from_bytes(unit), value }))
+        }
+        b"TEXT" => map(parse_unicode_string, DescriptorValue::String)(input),
+        b"enum" => {
+            let (input, type_id) = parse_id(input)?;
+            let (input, value) = parse_id(input)?;
+            Ok((input, DescriptorValue::Enumerated { type_id, value }))
+        }
+        b"long" => map(be_i32, DescriptorValue::Integer)(input),
+        b"comp" => map(be_i64, DescriptorValue::LargeInteger)(input),
+        b"bool" => map(be_u8, |value| DescriptorValue::Boolean(value != 0))(input),
+        b"type" | b"GlbC" => {
+            let (input, class_name) = parse_unicode_string(input)?;
+            let (input, class_id) = parse_id(input)?;
+            Ok((input, DescriptorValue::Class { class_name, class_id }))
+        }
+        b"alis" => {
+            let (input, len) = be_u32(input)?;
+            map(take(len), |data| DescriptorValue::Alias(Cow::Borrowed(data)))(input)
+        }
+        b"tdta" => {
+            let (input, len) = be_u32(input)?;
+            map(take(len), |data| DescriptorValue::RawData(Cow::Borrowed(data)))(input)
+        }
+        b"ObAr" => {
+            let (input, count) = be_u32(input)?;
+            let (input, descriptor) = parse_descriptor(input, depth)?;
+            Ok((input, DescriptorValue::ObjectArray { count, descriptor }))
+        }
+        b"UnFl" => {
+            let (input, unit) = parse_os_type(input)?;
+            let (input, len) = be_u32(input)?;
+            let (input, values) = count(be_f64, len as usize)(input)?;
+            Ok((input, DescriptorValue::UnitFloats { unit: FloatUnit::from_bytes(unit), values }))
+        }
+        _ => {
+            let (input, len) = be_u32(input)?;
+            map(take(len), |data| DescriptorValue::Raw { os_type, data: Cow::Borrowed(data) })(input)
+        }
+    }
+}
+
+fn parse_reference_item(input: &[u8]) -> IResult<&[u8], ReferenceItem> {
+    let (input, os_type) = parse_os_type(input)?;
+    match &os_type {
+        b"prop" => {
+            let (input, class_name) = parse_unicode_string(input)?;
+            let (input, class_id) = parse_id(input)?;
+            let (input, key_id) = parse_id(input)?;
+            Ok((input, ReferenceItem::Property { class_name, class_id, key_id }))
+        }
+        b"Clss" => {
+            let (input, class_name) = parse_unicode_string(input)?;
+            let (input, class_id) = parse_id(input)?;
+            Ok((input, ReferenceItem::Class { class_name, class_id }))
+        }
+        b"Enmr" => {
+            let (input, class_name) = parse_unicode_string(input)?;
+            let (input, class_id) = parse_id(input)?;
+            let (input, type_id) = parse_id(input)?;
+            let (input, value) = parse_id(input)?;
+            Ok((input, ReferenceItem::Enumerated { class_name, class_id, type_id, value }))
+        }
+        b"rele" => {
+            let (input, class_name) = parse_unicode_string(input)?;
+            let (input, class_id) = parse_id(input)?;
+            let (input, offset) = be_u32(input)?;
+            Ok((input, ReferenceItem::Offset { class_name, class_id, offset }))
+        }
+        b"Idnt" => map(be_u32, ReferenceItem::Identifier)(input),
+        b"indx" => map(be_u32, ReferenceItem::Index)(input),
+        b"name" => {
+            let (input, class_name) = parse_unicode_string(input)?;
+            let (input, class_id) = parse_id(input)?;
+            let (input, name) = parse_unicode_string(input)?;
+            Ok((input, ReferenceItem::Name { class_name, class_id, name }))
+        }
+        _ => Err(nom::Err::Error(Error::new(input, ErrorKind::Switch))),
+    }
+}
@@ -0,0 +1,35 @@
+use nom::number::complete::be_u32;
+use nom::IResult;
+
+use crate::descriptor::{parse_versioned_descriptor, Descriptor};
+
+/// Layer effects stored as a descriptor (`lfx2`, or `lmfx` when an effect is applied more than once).
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct LayerEffects<'a> {
+    descriptor: Descriptor<'a>,
+}
+
+impl<'a> LayerEffects<'a> {
+    /// The scale of the effects in percent (`Scl `), by which sizes and distances of the effects are multiplied. 100 when not stored.
+    pub fn scale(&self) -> f64 {
+        self.descriptor.get("Scl ").and_then(|scale| scale.as_f64()).unwrap_or(100.0)
+    }
+    /// Whether the effects are turned on as a whole (`masterFXSwitch`).
+    pub fn enabled(&self) -> bool {
+        self.descriptor.get("masterFXSwitch").and_then(|switch| switch.as_bool()).unwrap_or(true)
+    }
+    /// The whole effects descriptor, each effect being an item such as `DrSh` (drop shadow) or `OrGl` (outer glow).
+    pub fn descriptor(&self) -> &Descriptor<'a> {
+        &self.descriptor
+    }
+    pub(crate) fn into_static(self) -> LayerEffects<'static> {
+        LayerEffects { descriptor: self.descriptor.into_static() }
+    }
+}
+
+pub(crate) fn parse_layer_effects(input: &[u8]) -> IResult<&[u8], LayerEffects<'_>> {
+    let (input, _object_effects_version) = be_u32(input)?;
+    let (input, descriptor) = parse_versioned_descriptor(input)?;
+    Ok((input, LayerEffects { descriptor }))
+}
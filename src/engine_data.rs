@@ -0,0 +1,191 @@
+//! EngineData, the PostScript-like text format text layers keep their styling in.
+
+use nom::bytes::complete::{tag, take_while, take_while1};
+use nom::error::{Error, ErrorKind};
+use nom::IResult;
+
+/// A value of EngineData, the dictionaries holding the paragraph and character styles of a text layer.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum EngineValue {
+    /// `<< /Key value ... >>`, the items in the order they are written.
+    Dict(Vec<(String, EngineValue)>),
+    /// `[ value ... ]`
+    Array(Vec<EngineValue>),
+    Integer(i64),
+    /// A number written with a decimal point, such as `.5` or `12.0`.
+    Float(f64),
+    Bool(bool),
+    /// `(text)`, decoded from UTF-16 when it starts with a byte order mark.
+    String(String),
+    /// `/Name` used as a value.
+    Name(String),
+}
+
+impl EngineValue {
+    /// The value of `key` in a dictionary, `None` for anything else.
+    pub fn get(&self, key: &str) -> Option<&EngineValue> {
+        match self {
+            EngineValue::Dict(items) => items.iter().find(|(k, _)| k == key).map(|(_, value)| value),
+            _ => None,
+        }
+    }
+    pub fn as_dict(&self) -> Option<&[(String, EngineValue)]> {
+        match self {
+            EngineValue::Dict(items) => Some(items),
+            _ => None,
+        }
+    }
+    pub fn as_array(&self) -> Option<&[EngineValue]> {
+        match self {
+            EngineValue::Array(values) => Some(values),
+            _ => None,
+        }
+    }
+    pub fn as_i64(&self) -> Option<i64> {
+        match self {
+            EngineValue::Integer(value) => Some(*value),
+            _ => None,
+        }
+    }
+    /// The number of an integer or a float, which EngineData doesn't tell apart for values such as font sizes.
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            EngineValue::Integer(value) => Some(*value as f64),
+            EngineValue::Float(value) => Some(*value),
+            _ => None,
+        }
+    }
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            EngineValue::Bool(value) => Some(*value),
+            _ => None,
+        }
+    }
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            EngineValue::String(value) => Some(value),
+            _ => None,
+        }
+    }
+    pub fn as_name(&self) -> Option<&str> {
+        match self {
+            EngineValue::Name(value) => Some(value),
+            _ => None,
+        }
+    }
+}
+
+fn is_space(c: u8) -> bool {
+    matches!(c, b' ' | b'\t' | b'\r' | b'\n' | b'\0')
+}
+
+fn is_delimiter(c: u8) -> bool {
+    is_space(c) || matches!(c, b'/' | b'[' | b']' | b'<' | b'>' | b'(' | b')')
+}
+
+fn space(input: &[u8]) -> &[u8] {
+    let (input, _) = take_while::<_, _, Error<&[u8]>>(is_space)(input).unwrap();
+    input
+}
+
+fn parse_name(input: &[u8]) -> IResult<&[u8], String> {
+    let (input, _) = tag("/")(input)?;
+    let (input, name) = take_while(|c| !is_delimiter(c))(input)?;
+    Ok((input, String::from_utf8_lossy(name).into_owned()))
+}
+
+/// A parenthesized string, UTF-16BE when it starts with a byte order mark.
+fn parse_string(input: &[u8]) -> IResult<&[u8], String> {
+    let (mut input, _) = tag("(")(input)?;
+    let mut bytes = Vec::new();
+    loop {
+        match *input {
+            [b'\\', c, ..] => {
+                bytes.push(c);
+                input = &input[2..];
+            }
+            [b')', ..] => break,
+            [c, ..] => {
+                bytes.push(c);
+                input = &input[1..];
+            }
+            [] => return Err(nom::Err::Error(Error::new(input, ErrorKind::Eof))),
+        }
+    }
+    let string = match bytes.strip_prefix(&[0xfe, 0xff][..]) {
+        Some(utf16) => String::from_utf16_lossy(&utf16.chunks_exact(2).map(|pair| u16::from_be_bytes([pair[0], pair[1]])).collect::<Vec<_>>()),
+        None => bytes.iter().map(|&c| c as char).collect(),
+    };
+    Ok((&input[1..], string))
+}
+
+/// How deep dictionaries and arrays can nest, past which the data fails to parse rather than overflowing the stack.
+const MAX_DEPTH: usize = 64;
+
+/// Parses a value inside `depth` dictionaries and arrays.
+fn parse_value(input: &[u8], depth: usize) -> IResult<&[u8], EngineValue> {
+    let input = space(input);
+    if depth == MAX_DEPTH && matches!(input.first(), Some(b'<' | b'[')) {
+        return Err(nom::Err::Error(Error::new(input, ErrorKind::TooLarge)));
+    }
+    match input.first() {
+        Some(b'<') => {
+            let (mut input, _) = tag("<<")(input)?;
+            let mut items = Vec::new();
+            loop {
+                input = space(input);
+                if let Ok((i, _)) = tag::<_, _, Error<&[u8]>>(">>")(input) {
+                    return Ok((i, EngineValue::Dict(items)));
+                }
+                let (i, key) = parse_name(input)?;
+                let (i, value) = parse_value(i, depth + 1)?;
+                items.push((key, value));
+                input = i;
+            }
+        }
+        Some(b'[') => {
+            let mut input = &input[1..];
+            let mut values = Vec::new();
+            loop {
+                input = space(input);
+                if let Some(i) = input.strip_prefix(&b"]"[..]) {
+                    return Ok((i, EngineValue::Array(values)));
+                }
+                let (i, value) = parse_value(input, depth + 1)?;
+                values.push(value);
+                input = i;
+            }
+        }
+        Some(b'(') => {
+            let (input, string) = parse_string(input)?;
+            Ok((input, EngineValue::String(string)))
+        }
+        Some(b'/') => {
+            let (input, name) = parse_name(input)?;
+            Ok((input, EngineValue::Name(name)))
+        }
+        _ => {
+            let (rest, token) = take_while1(|c| !is_delimiter(c))(input)?;
+            let token = String::from_utf8_lossy(token);
+            let value = match &*token {
+                "true" => EngineValue::Bool(true),
+                "false" => EngineValue::Bool(false),
+                token => match token.parse::<i64>() {
+                    Ok(value) => EngineValue::Integer(value),
+                    Err(_) => match token.parse::<f64>() {
+                        Ok(value) => EngineValue::Float(value),
+                        Err(_) => return Err(nom::Err::Error(Error::new(input, ErrorKind::Float))),
+                    },
+                },
+            };
+            Ok((rest, value))
+        }
+    }
+}
+
+/// Parses the dictionary stored as the `EngineData` of a text layer, or in the `Txt2` block of the document.
+/// Dictionaries and arrays nested more than 64 deep fail.
+pub(crate) fn parse_engine_data(input: &[u8]) -> IResult<&[u8], EngineValue> {
+    parse_value(input, 0)
+}
@@ -0,0 +1,109 @@
+use std::fmt;
+use std::io;
+
+use crate::layer_info::ImageCompression;
+
+/// Errors of parsing a document and of the operations on a parsed one.
+#[derive(Debug)]
+pub enum PsdError {
+    /// A signature (`8BPS`, `8BIM`, `8B64`) doesn't match at `offset`. At offset 0 the input isn't a PSD at all.
+    BadSignature { offset: usize },
+    /// A version of the file format other than 1 (PSD) and 2 (PSB).
+    UnsupportedVersion(u16),
+    /// The header has a channel count outside `1..=56`.
+    InvalidChannelCount(u16),
+    /// The header has a width or height outside `1..=30000`, or `1..=300000` for a PSB.
+    InvalidDimensions { width: u32, height: u32 },
+    /// The header has a depth other than 1, 8, 16 or 32.
+    UnsupportedDepth(u16),
+    /// The input ends in the middle of `section`.
+    Truncated { section: &'static str },
+    /// `section` has content that can't be read, found at `offset` in the input.
+    Malformed { section: &'static str, offset: usize },
+    /// A compression method that isn't defined, `offset` being its position in the input.
+    UnknownCompression { value: u16, offset: usize },
+    /// Data uses a compression method the operation can't decode.
+    UnsupportedCompression(ImageCompression),
+    /// The header has a color mode value that isn't defined (such as the unused 5 and 6), `offset` being its position in the input.
+    UnknownColorMode { value: u16, offset: usize },
+    /// The fields of the extra data of a layer record don't add up to its length of `len` bytes, stored at `offset`.
+    LayerRecordLengthMismatch { offset: usize, len: usize },
+    /// An image to draw into doesn't have the size of the rectangle of the document it is said to cover.
+    CanvasSizeMismatch { canvas: (u32, u32), rect: (u32, u32) },
+    /// Zip compressed data that isn't a valid zlib stream, with the error of the decoder.
+    InvalidZipData(io::Error),
+    /// Zip compressed data inflates to `actual` bytes, where the size and depth of the channel call for `expected`.
+    /// Data inflating to more is only read up to `expected + 1` bytes, which is then `actual`.
+    ZipSizeMismatch { expected: usize, actual: usize },
+    /// RLE compressed data decompresses to `actual` bytes, where the size and depth of the channel call for `expected`.
+    /// A packet cut short by the end of the data counts the bytes that are there.
+    RleSizeMismatch { expected: usize, actual: usize },
+    /// The image data decompresses to `actual` bytes, where the size and depth of the header call for `expected`.
+    ImageDataSizeMismatch { expected: usize, actual: usize },
+    /// The document uses something the operation can't handle, such as a color mode or depth.
+    Unsupported(String),
+    /// Image data whose size in bytes doesn't fit in a `usize` of the target, which can happen for large documents where it is 32 bits.
+    TooLarge,
+    /// The image data lacks a channel the color mode needs.
+    MissingChannel(usize),
+    /// Reading the input failed.
+    Io(io::Error),
+    /// The ICC profile couldn't be loaded or used for a conversion.
+    #[cfg(feature = "color-management")]
+    ColorManagement(lcms2::Error),
+}
+
+impl fmt::Display for PsdError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PsdError::BadSignature { offset: 0 } => write!(f, "not a PSD file"),
+            PsdError::BadSignature { offset } => write!(f, "bad signature at offset {}", offset),
+            PsdError::UnsupportedVersion(version) => write!(f, "unsupported version {}", version),
+            PsdError::InvalidChannelCount(channels) => write!(f, "invalid channel count {}", channels),
+            PsdError::InvalidDimensions { width, height } => write!(f, "invalid dimensions {}x{}", width, height),
+            PsdError::UnsupportedDepth(depth) => write!(f, "unsupported depth {}", depth),
+            PsdError::Truncated { section } => write!(f, "the input ends in the {}", section),
+            PsdError::Malformed { section, offset } => write!(f, "malformed {} at offset {}", section, offset),
+            PsdError::UnknownCompression { value, offset } => write!(f, "unknown compression {} at offset {}", value, offset),
+            PsdError::UnsupportedCompression(compression) => write!(f, "{:?} compression is not supported", compression),
+            PsdError::UnknownColorMode { value, offset } => write!(f, "unknown color mode {} at offset {}", value, offset),
+            PsdError::LayerRecordLengthMismatch { offset, len } => write!(f, "the {} bytes of layer record extra data at offset {} don't match its fields", len, offset),
+            PsdError::CanvasSizeMismatch { canvas, rect } => write!(f, "a {}x{} canvas can't cover a {}x{} rectangle", canvas.0, canvas.1, rect.0, rect.1),
+            PsdError::InvalidZipData(_) => write!(f, "invalid zip compressed data"),
+            PsdError::ZipSizeMismatch { expected, actual } => write!(f, "zip compressed data inflates to {} bytes instead of {}", actual, expected),
+            PsdError::RleSizeMismatch { expected, actual } => write!(f, "RLE compressed data decompresses to {} bytes instead of {}", actual, expected),
+            PsdError::ImageDataSizeMismatch { expected, actual } => write!(f, "image data decompresses to {} bytes instead of {}", actual, expected),
+            PsdError::Unsupported(what) => write!(f, "{} is not supported", what),
+            PsdError::TooLarge => write!(f, "the image data is too large for this target"),
+            PsdError::MissingChannel(index) => write!(f, "image data has no channel {}", index),
+            PsdError::Io(_) => write!(f, "reading the input failed"),
+            #[cfg(feature = "color-management")]
+            PsdError::ColorManagement(_) => write!(f, "color management failed"),
+        }
+    }
+}
+
+impl std::error::Error for PsdError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            PsdError::InvalidZipData(error) => Some(error),
+            PsdError::Io(error) => Some(error),
+            #[cfg(feature = "color-management")]
+            PsdError::ColorManagement(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for PsdError {
+    fn from(error: io::Error) -> Self {
+        PsdError::Io(error)
+    }
+}
+
+#[cfg(feature = "color-management")]
+impl From<lcms2::Error> for PsdError {
+    fn from(error: lcms2::Error) -> Self {
+        PsdError::ColorManagement(error)
+    }
+}
@@ -0,0 +1,131 @@
+use nom::IResult;
+
+use crate::color::{cmyk_to_rgb, hsb_to_rgb, lab_to_rgb, to_u8};
+use crate::descriptor::{parse_versioned_descriptor, Descriptor};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum GradientStyle {
+    Linear,
+    Radial,
+    Angle,
+    Reflected,
+    Diamond,
+}
+
+impl GradientStyle {
+    fn from_key(key: &str) -> Option<Self> {
+        match key {
+            "Lnr " => Some(GradientStyle::Linear),
+            "Rdl " => Some(GradientStyle::Radial),
+            "Angl" => Some(GradientStyle::Angle),
+            "Rflc" => Some(GradientStyle::Reflected),
+            "Dmnd" => Some(GradientStyle::Diamond),
+            _ => None,
+        }
+    }
+}
+
+/// Gradient fill setting (`GdFl`).
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Gradient {
+    stops: Vec<(f64, [u8; 4])>,
+    angle: f64,
+    style: GradientStyle,
+    scale: f64,
+    reverse: bool,
+}
+
+impl Gradient {
+    /// Color stops as `(location, [r, g, b, a])` with the location in `0.0..=1.0`.
+    /// The alpha is interpolated from the transparency stops at the location of each color stop.
+    pub fn stops(&self) -> &[(f64, [u8; 4])] {
+        &self.stops
+    }
+    /// Angle in degrees.
+    pub fn angle(&self) -> f64 {
+        self.angle
+    }
+    pub fn style(&self) -> GradientStyle {
+        self.style
+    }
+    /// Scale as a factor, `1.0` is 100%.
+    pub fn scale(&self) -> f64 {
+        self.scale
+    }
+    pub fn reverse(&self) -> bool {
+        self.reverse
+    }
+
+    pub(crate) fn from_descriptor(descriptor: &Descriptor) -> Option<Self> {
+        let gradient = descriptor.get("Grad")?.as_descriptor()?;
+        let location = |stop: &Descriptor| stop.get("Lctn").and_then(|value| value.as_f64()).unwrap_or(0.0) / 4096.0;
+        let transparency = gradient
+            .get("Trns")
+            .and_then(|value| value.as_list())
+            .unwrap_or(&[])
+            .iter()
+            .filter_map(|stop| stop.as_descriptor())
+            .map(|stop| (location(stop), stop.get("Opct").and_then(|value| value.as_f64()).unwrap_or(100.0) / 100.0))
+            .collect::<Vec<_>>();
+        let stops = gradient
+            .get("Clrs")?
+            .as_list()?
+            .iter()
+            .filter_map(|stop| stop.as_descriptor())
+            .map(|stop| {
+                let location = location(stop);
+                let [r, g, b] = stop.get("Clr ").and_then(|value| value.as_descriptor()).and_then(descriptor_color).unwrap_or([0.0; 3]);
+                (location, [to_u8(r), to_u8(g), to_u8(b), to_u8(opacity_at(&transparency, location))])
+            })
+            .collect();
+        Some(Gradient {
+            stops,
+            angle: descriptor.get("Angl").and_then(|value| value.as_f64()).unwrap_or(0.0),
+            style: descriptor.get("Type").and_then(|value| value.as_enum()).and_then(GradientStyle::from_key).unwrap_or(GradientStyle::Linear),
+            scale: descriptor.get("Scl ").and_then(|value| value.as_f64()).unwrap_or(100.0) / 100.0,
+            reverse: descriptor.get("Rvrs").and_then(|value| value.as_bool()).unwrap_or(false),
+        })
+    }
+}
+
+fn opacity_at(stops: &[(f64, f64)], location: f64) -> f64 {
+    match stops.iter().position(|&(stop, _)| stop >= location) {
+        None => stops.last().map_or(1.0, |&(_, opacity)| opacity),
+        Some(0) => stops[0].1,
+        Some(index) => {
+            let (start, from) = stops[index - 1];
+            let (end, to) = stops[index];
+            if end <= start {
+                to
+            } else {
+                from + (to - from) * (location - start) / (end - start)
+            }
+        }
+    }
+}
+
+/// Converts a color descriptor (`RGBC`, `HSBC`, `CMYC`, `Grsc` or `LbCl`) into RGB in `0.0..=1.0`.
+pub(crate) fn descriptor_color(color: &Descriptor) -> Option<[f64; 3]> {
+    let component = |key: &str| color.get(key).and_then(|value| value.as_f64());
+    match color.class_id() {
+        "RGBC" => match (component("Rd  "), component("Grn "), component("Bl  ")) {
+            (Some(r), Some(g), Some(b)) => Some([r / 255.0, g / 255.0, b / 255.0]),
+            _ => Some([component("redFloat")?, component("greenFloat")?, component("blueFloat")?]),
+        },
+        "HSBC" => Some(hsb_to_rgb(component("H   ")?, component("Strt")? / 100.0, component("Brgh")? / 100.0)),
+        "CMYC" => Some(cmyk_to_rgb(component("Cyn ")? / 100.0, component("Mgnt")? / 100.0, component("Ylw ")? / 100.0, component("Blck")? / 100.0)),
+        "Grsc" => {
+            let gray = 1.0 - component("Gry ")? / 100.0;
+            Some([gray; 3])
+        }
+        "LbCl" => Some(lab_to_rgb(component("Lmnc")?, component("A   ")?, component("B   ")?)),
+        _ => None,
+    }
+}
+
+pub(crate) fn parse_gradient_fill(data: &[u8]) -> IResult<&[u8], Option<Gradient>> {
+    let (data, descriptor) = parse_versioned_descriptor(data)?;
+    Ok((data, Gradient::from_descriptor(&descriptor)))
+}
@@ -0,0 +1,21 @@
+//! The fixed-point number formats of the PSD format.
+
+/// 16.16 fixed point, as the resolution info stores the resolutions.
+pub fn fixed_16_16(value: u32) -> f64 {
+    value as f64 / 65536.0
+}
+
+/// 8.8 fixed point.
+pub fn fixed_8_8(value: u16) -> f64 {
+    value as f64 / 256.0
+}
+
+/// Signed 8.24 fixed point, the coordinates of path points relative to the document size.
+pub fn fixed_8_24(value: i32) -> f64 {
+    value as f64 / (1 << 24) as f64
+}
+
+/// Signed 27.5 fixed point, guide locations in 1/32 pixels.
+pub fn fixed_27_5(value: i32) -> f64 {
+    value as f64 / 32.0
+}
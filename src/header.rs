@@ -1,9 +1,15 @@
+use std::convert::TryFrom;
+
 use nom::bytes::complete::tag;
-use nom::combinator::{map_res, verify};
-use nom::number::complete::{be_u16, be_u32};
+use nom::combinator::{map, map_res, verify};
+use nom::number::complete::{be_u16, be_u32, be_u64};
 use nom::IResult;
 
+use crate::signature::{signature, PSD};
+use crate::ParseOptions;
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Ord, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum ColorMode {
     Bitmap = 0,
     Grayscale = 1,
@@ -16,7 +22,7 @@ pub enum ColorMode {
 }
 
 impl ColorMode {
-    fn from_u16(value: u16) -> Result<Self, u16> {
+    pub fn from_u16(value: u16) -> Result<Self, u16> {
         match value {
             0 => Ok(ColorMode::Bitmap),
             1 => Ok(ColorMode::Grayscale),
@@ -31,9 +37,79 @@ impl ColorMode {
     }
 }
 
-#[derive(Debug, PartialEq, Eq)]
+impl TryFrom<u16> for ColorMode {
+    type Error = u16;
+    fn try_from(value: u16) -> Result<Self, Self::Error> {
+        ColorMode::from_u16(value)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum Version {
+    /// Version 1, a PSD file.
+    Psd = 1,
+    /// Version 2, the large document format (PSB) for documents of up to 300,000 pixels, in which some length fields are 8 bytes.
+    Psb = 2,
+}
+
+impl Version {
+    pub fn from_u16(value: u16) -> Result<Self, u16> {
+        match value {
+            1 => Ok(Version::Psd),
+            2 => Ok(Version::Psb),
+            _ => Err(value),
+        }
+    }
+    /// The largest width and height of a document.
+    pub fn max_dimension(self) -> u32 {
+        match self {
+            Version::Psd => 30_000,
+            Version::Psb => 300_000,
+        }
+    }
+    /// Bytes in the length of the layer and mask information, the layer info and a layer channel.
+    pub(crate) fn length_size(self) -> usize {
+        match self {
+            Version::Psd => 4,
+            Version::Psb => 8,
+        }
+    }
+    /// Bytes in the length of an additional layer information block, 8 in a PSB only for the keys that can hold large data.
+    pub(crate) fn block_length_size(self, key: &[u8]) -> usize {
+        match self {
+            Version::Psb if LARGE_BLOCK_KEYS.iter().any(|large| &large[..] == key) => 8,
+            _ => 4,
+        }
+    }
+    /// Bytes in each of the byte counts in front of RLE compressed rows.
+    pub(crate) fn byte_count_size(self) -> usize {
+        match self {
+            Version::Psd => 2,
+            Version::Psb => 4,
+        }
+    }
+}
+
+const LARGE_BLOCK_KEYS: [&[u8; 4]; 13] = [b"LMsk", b"Lr16", b"Lr32", b"Layr", b"Mt16", b"Mt32", b"Mtrn", b"Alph", b"FMsk", b"lnk2", b"FEid", b"FXid", b"PxSD"];
+
+/// Reads a big-endian length of `size` (4 or 8) bytes.
+pub(crate) fn parse_length(input: &[u8], size: usize) -> IResult<&[u8], u64> {
+    match size {
+        8 => be_u64(input),
+        _ => map(be_u32, u64::from)(input),
+    }
+}
+
+/// The sum of the RLE byte counts in front of the data, each `size` bytes.
+pub(crate) fn sum_byte_counts(byte_counts: &[u8], size: usize) -> usize {
+    byte_counts.chunks_exact(size).map(|count| count.iter().fold(0, |sum, &byte| sum << 8 | byte as usize)).sum()
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct PsdHeader {
-    version: u16,
+    version: Version,
     channels: u16,
     height: u32,
     width: u32,
@@ -42,7 +118,7 @@ pub struct PsdHeader {
 }
 
 impl PsdHeader {
-    pub fn version(&self) -> u16 {
+    pub fn version(&self) -> Version {
         self.version
     }
     pub fn channels(&self) -> u16 {
@@ -62,14 +138,15 @@ impl PsdHeader {
     }
 }
 
-pub(crate) fn parse_header(input: &[u8]) -> IResult<&[u8], PsdHeader> {
-    let (input, _) = tag(b"8BPS")(input)?;
-    let (input, _) = verify(be_u16, |version| *version == 1)(input)?;
+pub(crate) fn parse_header<'a>(input: &'a [u8], options: &ParseOptions) -> IResult<&'a [u8], PsdHeader> {
+    let (input, _) = signature(input, &[PSD], options)?;
+    let (input, version) = verify(be_u16, |version| *version == 1 || *version == 2)(input)?;
+    let version = if version == 1 { Version::Psd } else { Version::Psb };
     let (input, _) = tag(&[0u8, 0, 0, 0, 0, 0])(input)?;
     let (input, channels) = verify(be_u16, |channels| (1..=56).contains(channels))(input)?;
-    let (input, height) = verify(be_u32, |height| (1..=30_000).contains(height))(input)?;
-    let (input, width) = verify(be_u32, |width| (1..=30_000).contains(width))(input)?;
+    let (input, height) = verify(be_u32, |height| (1..=version.max_dimension()).contains(height))(input)?;
+    let (input, width) = verify(be_u32, |width| (1..=version.max_dimension()).contains(width))(input)?;
     let (input, depth) = verify(be_u16, |depth| [1, 8, 16, 32].contains(depth))(input)?;
     let (input, color_mode) = map_res(be_u16, ColorMode::from_u16)(input)?;
-    Ok((input, PsdHeader { version: 1, channels, height, width, depth, color_mode }))
+    Ok((input, PsdHeader { version, channels, height, width, depth, color_mode }))
 }
@@ -1,84 +1,178 @@
 use std::borrow::Cow;
 
+use nom::bytes::complete::take;
 use nom::combinator::map_res;
+use nom::error::{Error, ErrorKind};
 use nom::number::complete::be_u16;
 use nom::IResult;
 use once_cell::sync::OnceCell;
 
-use crate::header::PsdHeader;
-use crate::layer_info::ImageCompression;
+use crate::error::PsdError;
+use crate::header::{sum_byte_counts, ColorMode, PsdHeader, Version};
+use crate::inflate::{decompress_zip, inflate};
+use crate::layer_info::{unpack_bits, ImageCompression};
+use crate::{owned_len, plane_len};
 
-#[derive(Debug, Eq, PartialEq)]
+#[derive(Debug, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct ImageData<'a> {
     compression: ImageCompression,
+    #[cfg_attr(feature = "serde", serde(serialize_with = "crate::serialize::len"))]
     data: Cow<'a, [u8]>,
+    #[cfg_attr(feature = "serde", serde(skip))]
     raw_data: OnceCell<Vec<Cow<'a, [u8]>>>,
     width: u32,
     height: u32,
     channels: u16,
+    depth: u16,
+    version: Version,
 }
 
 impl<'a> ImageData<'a> {
     pub fn compression(&self) -> ImageCompression {
         self.compression
     }
+    /// The image data after the compression method, as stored in the file.
+    pub(crate) fn compressed_data(&self) -> &[u8] {
+        &self.data
+    }
+    /// The decompressed channels, row by row. Samples deeper than 8 bits are big-endian, and 1-bit images pack 8 pixels into a byte,
+    /// so a row is `ceil(width * depth / 8)` bytes.
+    ///
+    /// Panics when the data can't be decompressed, see [`try_raw_data`](Self::try_raw_data).
     pub fn raw_data(&self) -> &[Cow<'a, [u8]>] {
-        self.raw_data.get_or_init(|| {
+        self.try_raw_data().unwrap_or_else(|error| panic!("{}", error))
+    }
+    /// [`raw_data`](Self::raw_data), failing for zip compressed data without the `zip` feature, or when the data doesn't decompress to the size of the image.
+    pub fn try_raw_data(&self) -> Result<&[Cow<'a, [u8]>], PsdError> {
+        let raw_data = self.raw_data.get_or_try_init(|| -> Result<_, PsdError> {
             let mut list = Vec::with_capacity(self.channels as usize);
+            let len_one_channel = plane_len(self.width, self.height, self.depth, 1)?;
             match self.compression {
-                ImageCompression::Raw => {
-                    let len_one_channel = self.height as usize * self.width as usize;
-                    let mut data = match self.data {
-                        Cow::Borrowed(data) => data,
-                        Cow::Owned(_) => unreachable!(),
-                    };
-                    // let mut data = self.data.deref();
-                    while !data.is_empty() {
-                        let (one_channel, follow) = data.split_at(len_one_channel);
-                        list.push(Cow::Borrowed(one_channel));
+                ImageCompression::Raw => match self.data {
+                    Cow::Borrowed(data) => list.extend(data.chunks(len_one_channel.max(1)).map(Cow::Borrowed)),
+                    // owned data that isn't decoded yet, from `Psd::clone_owned`
+                    Cow::Owned(ref data) => list.extend(data.chunks(len_one_channel.max(1)).map(|channel| Cow::Owned(channel.to_vec()))),
+                },
+                ImageCompression::RLE => {
+                    let size = self.version.byte_count_size();
+                    let rows = self.height as usize * size;
+                    let byte_counts = self.data.get(..rows * self.channels as usize).unwrap_or(&self.data);
+                    let mut data = self.data.get(byte_counts.len()..).unwrap_or(&[]);
+                    for channel in 0..self.channels as usize {
+                        // the rows of each channel are decoded on their own, so a short channel doesn't shift the next one
+                        let channel_len = sum_byte_counts(byte_counts.get(channel * rows..(channel + 1) * rows).unwrap_or(&[]), size);
+                        let (channel_data, follow) = data.split_at(channel_len.min(data.len()));
+                        list.push(Cow::Owned(unpack_bits(channel_data, len_one_channel)?));
                         data = follow;
                     }
                 }
-                ImageCompression::RLE => {
-                    let mut data = &self.data[self.height as usize * self.channels as usize * 2..];
-                    for _ in 0..self.channels {
-                        let mut data_one_channel = Vec::with_capacity(self.width as usize * self.height as usize);
-                        while data_one_channel.len() < self.width as usize * self.height as usize {
-                            let (&len, follow) = data.split_first().unwrap();
-                            match len as i8 {
-                                len @ 0..=127 => {
-                                    let len = len as usize;
-                                    data_one_channel.extend(&follow[..len + 1]);
-                                    data = &follow[len + 1..];
-                                }
-                                len @ -127..=-1 => {
-                                    for _ in 0..-len as usize + 1 {
-                                        data_one_channel.push(follow[0]);
-                                    }
-                                    data = &follow[1..];
-                                }
-                                -128 => {
-                                    eprintln!("may be error");
-                                }
-                            }
-                        }
-                        assert_eq!(data_one_channel.len(), self.width as usize * self.height as usize);
-                        list.push(Cow::Owned(data_one_channel));
-                    }
+                compression @ (ImageCompression::ZipWithoutPrediction | ImageCompression::ZipWithPrediction) => {
+                    // a single stream holds all the channels
+                    let data = decompress_zip(&self.data, compression, self.width, self.height, self.depth, self.channels as usize)?;
+                    list.extend(data.chunks(len_one_channel.max(1)).map(|channel| Cow::Owned(channel.to_vec())));
                 }
-                ImageCompression::ZipWithoutPrediction | ImageCompression::ZipWithPrediction => {
-                    panic!("Zip compression is not supported");
+            }
+            Ok(list)
+        })?;
+        Ok(raw_data)
+    }
+    /// The decompressed channels like [`raw_data`](Self::raw_data), copied into vectors that don't borrow from the document.
+    /// Every channel is copied, which allocates as much again as the decoded image, so prefer `raw_data` when the document can be kept around.
+    pub fn raw_data_owned(&self) -> Vec<Vec<u8>> {
+        self.raw_data().iter().map(|channel| channel.to_vec()).collect()
+    }
+    /// The decompressed channels of a 16-bit document as samples, each channel `width * height` of them row by row.
+    /// Fails for other depths, and like [`try_raw_data`](Self::try_raw_data) for data that can't be decompressed.
+    pub fn raw_data_u16(&self) -> Result<Vec<Vec<u16>>, PsdError> {
+        if self.depth != 16 {
+            return Err(PsdError::Unsupported(format!("reading {}-bit image data as 16-bit samples", self.depth)));
+        }
+        Ok(self.try_raw_data()?.iter().map(|channel| channel.chunks_exact(2).map(|sample| u16::from_be_bytes([sample[0], sample[1]])).collect()).collect())
+    }
+    /// The decompressed channels of a 32-bit document as the floating point samples they store, linear and not clamped, see [`raw_data_u16`](Self::raw_data_u16).
+    pub fn raw_data_f32(&self) -> Result<Vec<Vec<f32>>, PsdError> {
+        if self.depth != 32 {
+            return Err(PsdError::Unsupported(format!("reading {}-bit image data as 32-bit samples", self.depth)));
+        }
+        Ok(self.try_raw_data()?.iter().map(|channel| channel.chunks_exact(4).map(|sample| f32::from_be_bytes([sample[0], sample[1], sample[2], sample[3]])).collect()).collect())
+    }
+    /// The channels of an 8-bit RGB document interleaved as `R, G, B, A`, `width * height * 4` bytes, taking the alpha from the fourth channel
+    /// and making the image opaque when there are only three. Unlike [`Psd::to_rgba8`](crate::Psd::to_rgba8) the samples are used as they are,
+    /// without any color conversion and without looking at whether the fourth channel is the transparency of the image or another alpha channel.
+    ///
+    /// Fails for other color modes and depths rather than misreading their samples.
+    pub fn to_rgba8(&self, header: &PsdHeader) -> Result<Vec<u8>, PsdError> {
+        if header.color_mode() != ColorMode::RGB {
+            return Err(PsdError::Unsupported(format!("interleaving {:?} image data as RGBA", header.color_mode())));
+        }
+        if self.depth != 8 {
+            return Err(PsdError::Unsupported(format!("interleaving {}-bit image data as RGBA", self.depth)));
+        }
+        let planes = self.try_raw_data()?;
+        if planes.len() < 3 {
+            return Err(PsdError::MissingChannel(planes.len()));
+        }
+        let (r, g, b, a) = (&planes[0], &planes[1], &planes[2], planes.get(3));
+        let pixels = plane_len(self.width, self.height, 8, 4)? / 4;
+        Ok((0..pixels).flat_map(|i| [r[i], g[i], b[i], a.map_or(255, |a| a[i])]).collect())
+    }
+    /// The number of bytes the data decompresses to, going through the RLE packets without decoding them.
+    /// Packets running past the byte count of their row only count the bytes that are there, and zip data is inflated up to one byte past the size of the image.
+    pub(crate) fn decoded_len(&self) -> Result<usize, PsdError> {
+        match self.compression {
+            ImageCompression::Raw => Ok(self.data.len()),
+            ImageCompression::RLE => {
+                let rows = self.height as usize * self.channels as usize;
+                let size = self.version.byte_count_size();
+                let (byte_counts, mut data) = self.data.split_at((rows * size).min(self.data.len()));
+                let mut len = 0;
+                for count in byte_counts.chunks_exact(size) {
+                    let count = sum_byte_counts(count, size);
+                    let (mut row, follow) = data.split_at(count.min(data.len()));
+                    data = follow;
+                    while let Some((&n, follow)) = row.split_first() {
+                        let (decoded, stored) = match n as i8 {
+                            n @ 0..=127 => (n as usize + 1, n as usize + 1),
+                            n @ -127..=-1 => (-n as usize + 1, 1),
+                            -128 => (0, 0),
+                        };
+                        len += if stored <= follow.len() { decoded } else { follow.len() };
+                        row = &follow[stored.min(follow.len())..];
+                    }
                 }
+                Ok(len)
+            }
+            compression @ (ImageCompression::ZipWithoutPrediction | ImageCompression::ZipWithPrediction) => {
+                // anything past the size of the image is a mismatch however long it is, so it isn't inflated
+                let limit = plane_len(self.width, self.height, self.depth, self.channels as usize)?;
+                Ok(inflate(&self.data, compression, limit)?.len())
             }
-            list
-        })
+        }
+    }
+    /// See `Psd::structurally_eq`. The samples are compared decoded, falling back to the stored bytes for data that can't be decoded.
+    pub(crate) fn structurally_eq(&self, other: &ImageData) -> bool {
+        let data_eq = match (self.try_raw_data(), other.try_raw_data()) {
+            (Ok(a), Ok(b)) => a == b,
+            _ => self.data == other.data,
+        };
+        (self.width, self.height, self.channels, self.depth) == (other.width, other.height, other.channels, other.depth) && data_eq
+    }
+    /// The owned data and the decoded channels, which borrow it for raw data.
+    pub(crate) fn memory_footprint(&self) -> usize {
+        owned_len(&self.data) + self.raw_data.get().map_or(0, |channels| channels.iter().map(owned_len).sum())
     }
-    pub(crate) fn into_static(self) -> ImageData<'static> {
-        let _ = self.raw_data();
-        let ImageData { compression, data, raw_data, width, height, channels } = self;
-        let raw_data = raw_data.into_inner().unwrap();
+    /// Without `decode`, channels that haven't been decoded yet stay that way and are decoded from the owned data when first used.
+    /// With it, data that can't be decoded is kept compressed as well.
+    pub(crate) fn into_static(self, decode: bool) -> ImageData<'static> {
+        if decode {
+            let _ = self.try_raw_data();
+        }
+        let ImageData { compression, data, raw_data, width, height, channels, depth, version } = self;
         let raw_data_cell = OnceCell::new();
-        raw_data_cell.set(raw_data.into_iter().map(Cow::into_owned).map(Cow::Owned).collect()).unwrap();
+        if let Some(raw_data) = raw_data.into_inner() {
+            raw_data_cell.set(raw_data.into_iter().map(Cow::into_owned).map(Cow::Owned).collect()).unwrap();
+        }
         ImageData {
             compression,
             data: Cow::Owned(data.into_owned()),
@@ -86,21 +180,38 @@ impl<'a> ImageData<'a> {
             width,
             height,
             channels,
+            depth,
+            version,
         }
     }
 }
 
-pub(crate) fn parse_image_data<'a, 'b>(input: &'a [u8], header: &'b PsdHeader) -> IResult<&'a [u8], ImageData<'a>> {
+pub(crate) fn parse_image_data<'a>(input: &'a [u8], header: &PsdHeader) -> IResult<&'a [u8], ImageData<'a>> {
     let (input, compression) = map_res(be_u16, ImageCompression::from_u16)(input)?;
+    let rows = header.height() as usize * header.channels() as usize;
+    let len = match compression {
+        ImageCompression::Raw => plane_len(header.width(), header.height(), header.depth(), header.channels() as usize).map_err(|_| nom::Err::Failure(Error::new(input, ErrorKind::TooLarge)))?,
+        ImageCompression::RLE => {
+            // the byte counts of a PSB are 4 bytes each
+            let size = header.version().byte_count_size();
+            let (_, byte_counts) = take(rows * size)(input)?;
+            rows * size + sum_byte_counts(byte_counts, size)
+        }
+        // the compressed size isn't recorded anywhere, so zip data is assumed to extend to the end of the input
+        ImageCompression::ZipWithoutPrediction | ImageCompression::ZipWithPrediction => input.len(),
+    };
+    let (follow, data) = take(len)(input)?;
     Ok((
-        &input[..0],
+        follow,
         ImageData {
             compression,
-            data: Cow::Borrowed(input),
+            data: Cow::Borrowed(data),
             raw_data: OnceCell::new(),
             width: header.width(),
             height: header.height(),
             channels: header.channels(),
+            depth: header.depth(),
+            version: header.version(),
         },
     ))
 }
@@ -1,13 +1,25 @@
 use std::borrow::Cow;
+use std::convert::TryInto;
 
-use nom::bytes::complete::tag;
-use nom::number::complete::{be_u16, be_u32, be_u8};
+use nom::bytes::complete::take;
+
+use nom::number::complete::{be_i32, be_u16, be_u32, be_u8};
 use nom::IResult;
 
-#[derive(Debug, Eq, PartialEq)]
+use crate::fixed::{fixed_16_16, fixed_27_5};
+use crate::path::{parse_path, Path};
+use crate::signature::{signature, BIM};
+use crate::slices::{parse_slices, Slices};
+use crate::strings::parse_unicode_string;
+use crate::{owned_len, ParseOptions};
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct ImageResourceBlock<'a> {
     resource_id: u16,
+    #[cfg_attr(feature = "serde", serde(serialize_with = "crate::serialize::text"))]
     name: Cow<'a, [u8]>,
+    #[cfg_attr(feature = "serde", serde(serialize_with = "crate::serialize::len"))]
     resource_data: Cow<'a, [u8]>,
 }
 
@@ -21,6 +33,27 @@ impl<'a> ImageResourceBlock<'a> {
     pub fn resource_data(&self) -> &[u8] {
         &self.resource_data
     }
+    /// The content of the block read by the layout its id stands for, [`ResourceContent::Raw`] for ids without one here
+    /// and for data that doesn't fit the layout.
+    pub fn decode(&self) -> ResourceContent<'_> {
+        let data = self.resource_data();
+        let content = match self.resource_id {
+            1005 => parse_resolution_info(data).ok().map(|(_, info)| ResourceContent::ResolutionInfo(info)),
+            1008 => parse_caption(data).map(ResourceContent::Caption),
+            1011 => parse_print_flags(data).map(ResourceContent::PrintFlags),
+            1032 => parse_grid_and_guides(data).ok().map(|(_, grid)| ResourceContent::GridAndGuides(grid)),
+            1033 | 1036 => parse_thumbnail(data).ok().map(|(_, thumbnail)| ResourceContent::Thumbnail(thumbnail)),
+            1034 => data.first().map(|&flag| ResourceContent::Copyrighted(flag != 0)),
+            1039 => Some(ResourceContent::IccProfile(data)),
+            1050 => parse_slices(data).ok().and_then(|(_, slices)| slices).map(ResourceContent::Slices),
+            1054 => parse_url_list(data).ok().map(|(_, urls)| ResourceContent::Urls(urls)),
+            1057 => parse_version_info(data).ok().map(|(_, info)| ResourceContent::VersionInfo(info)),
+            1060 => Some(ResourceContent::Xmp(String::from_utf8_lossy(data).into_owned())),
+            1025 | 2000..=2997 => parse_path(data).ok().map(|(_, path)| ResourceContent::Path(path)),
+            _ => None,
+        };
+        content.unwrap_or(ResourceContent::Raw(data))
+    }
     fn into_static(self) -> ImageResourceBlock<'static> {
         let ImageResourceBlock { resource_id, name, resource_data } = self;
         ImageResourceBlock {
@@ -31,44 +64,465 @@ impl<'a> ImageResourceBlock<'a> {
     }
 }
 
-#[derive(Debug, Eq, PartialEq)]
+#[derive(Debug, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct ImageResources<'a>(Vec<ImageResourceBlock<'a>>);
 
 impl<'a> ImageResources<'a> {
-    pub fn data(&self) -> &[ImageResourceBlock] {
+    pub fn data(&self) -> &[ImageResourceBlock<'a>] {
         &self.0
     }
+    pub fn urls(&self) -> Vec<(u32, String)> {
+        self.find(1054).and_then(|block| parse_url_list(block.resource_data()).ok()).map(|(_, urls)| urls).unwrap_or_default()
+    }
+    /// Caption (resource 1008), a Pascal string.
+    pub fn caption(&self) -> Option<String> {
+        parse_caption(self.find(1008)?.resource_data())
+    }
+    /// Copyright flag (resource 1034).
+    pub fn copyrighted(&self) -> Option<bool> {
+        self.find(1034)?.resource_data().first().map(|&flag| flag != 0)
+    }
+    /// XMP metadata packet (resource 1060).
+    pub fn xmp(&self) -> Option<String> {
+        self.find(1060).map(|block| String::from_utf8_lossy(block.resource_data()).into_owned())
+    }
+    /// Looks up a simple XMP property such as `xmp:CreateDate`, `xmp:ModifyDate` or `dc:creator` in [`xmp`](Self::xmp).
+    ///
+    /// This is not an XMP parser, only a best-effort scan of the packet text. It finds the property written as an attribute (`key="value"`)
+    /// or as an element, in which case the first piece of text inside it is returned (the first item of an `rdf:Seq`/`rdf:Alt`, for instance).
+    /// Namespace prefixes are matched literally.
+    pub fn xmp_field(&self, key: &str) -> Option<String> {
+        let xmp = self.xmp()?;
+        let unescape = |text: &str| text.replace("&lt;", "<").replace("&gt;", ">").replace("&quot;", "\"").replace("&apos;", "'").replace("&amp;", "&");
+        let mut search = xmp.as_str();
+        while let Some(index) = search.find(key) {
+            let before = search[..index].chars().next_back();
+            let after = &search[index + key.len()..];
+            search = after;
+            match before {
+                Some('<') if after.starts_with('>') || after.starts_with(char::is_whitespace) => {
+                    let end = after.find('>')?;
+                    if after[..end].ends_with('/') {
+                        continue;
+                    }
+                    let content = &after[end + 1..];
+                    let content = &content[..content.find(&format!("</{}", key))?];
+                    let text = content.split('<').map(|part| part.split_once('>').map_or(part, |(_, text)| text)).map(str::trim).find(|text| !text.is_empty());
+                    return Some(unescape(text.unwrap_or("")));
+                }
+                Some(c) if c.is_whitespace() => {
+                    let value = match after.trim_start().strip_prefix('=') {
+                        Some(value) => value.trim_start(),
+                        None => continue,
+                    };
+                    let quote = value.chars().next().filter(|&c| c == '"' || c == '\'')?;
+                    let value = &value[1..];
+                    return Some(unescape(&value[..value.find(quote)?]));
+                }
+                _ => {}
+            }
+        }
+        None
+    }
+    /// Slices (resource 1050), in either the binary or the descriptor based layout.
+    pub fn slices(&self) -> Option<Slices> {
+        self.find(1050).and_then(|block| parse_slices(block.resource_data()).ok()).and_then(|(_, slices)| slices)
+    }
+    /// The path stored in a path resource, the work path (1025) or a saved path (2000 to 2997).
+    pub fn path(&self, resource_id: u16) -> Option<Path> {
+        self.find(resource_id).and_then(|block| parse_path(block.resource_data()).ok()).map(|(_, path)| path)
+    }
+    /// Saved paths (resources 2000 to 2997) with their names.
+    pub fn paths(&self) -> Vec<(String, Path)> {
+        self.0
+            .iter()
+            .filter(|block| (2000..=2997).contains(&block.resource_id))
+            .filter_map(|block| Some((String::from_utf8_lossy(block.name()).into_owned(), parse_path(block.resource_data()).ok()?.1)))
+            .collect()
+    }
+    /// Resolution info (resource 1005).
+    pub fn resolution_info(&self) -> Option<ResolutionInfo> {
+        self.find(1005).and_then(|block| parse_resolution_info(block.resource_data()).ok()).map(|(_, info)| info)
+    }
+    /// Print flags (resource 1011).
+    pub fn print_flags(&self) -> Option<PrintFlags> {
+        parse_print_flags(self.find(1011)?.resource_data())
+    }
+    /// Version info (resource 1057), `None` when the resource is missing or malformed.
+    pub fn version_info(&self) -> Option<VersionInfo> {
+        self.find(1057).and_then(|block| parse_version_info(block.resource_data()).ok()).map(|(_, info)| info)
+    }
+    /// Grid and guides (resource 1032), `None` when the resource is missing or malformed.
+    pub fn grid_and_guides(&self) -> Option<GridAndGuides> {
+        self.find(1032).and_then(|block| parse_grid_and_guides(block.resource_data()).ok()).map(|(_, grid)| grid)
+    }
+    pub(crate) fn find(&self, resource_id: u16) -> Option<&ImageResourceBlock<'a>> {
+        self.0.iter().find(|block| block.resource_id == resource_id)
+    }
+    pub(crate) fn memory_footprint(&self) -> usize {
+        self.0.iter().map(|block| owned_len(&block.name) + owned_len(&block.resource_data)).sum()
+    }
     pub(crate) fn into_static(self) -> ImageResources<'static> {
         let ImageResources(list) = self;
         ImageResources(list.into_iter().map(ImageResourceBlock::into_static).collect())
     }
 }
 
-pub(crate) fn parse_image_resources(input: &[u8]) -> IResult<&[u8], ImageResources> {
+/// The content of an image resource block, see [`ImageResourceBlock::decode`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum ResourceContent<'a> {
+    /// 1005
+    ResolutionInfo(ResolutionInfo),
+    /// 1008
+    Caption(String),
+    /// 1011
+    PrintFlags(PrintFlags),
+    /// 1032
+    GridAndGuides(GridAndGuides),
+    /// 1033, or 1036 from Photoshop 5.0 on
+    Thumbnail(Thumbnail<'a>),
+    /// 1034
+    Copyrighted(bool),
+    /// 1039, the ICC profile as stored
+    IccProfile(&'a [u8]),
+    /// 1050
+    Slices(Slices),
+    /// 1054
+    Urls(Vec<(u32, String)>),
+    /// 1057
+    VersionInfo(VersionInfo),
+    /// 1060
+    Xmp(String),
+    /// The work path (1025) or a saved path (2000 to 2997).
+    Path(Path),
+    Raw(&'a [u8]),
+}
+
+/// A thumbnail resource (1033 or 1036).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Thumbnail<'a> {
+    format: u32,
+    width: u32,
+    height: u32,
+    bits_per_pixel: u16,
+    data: &'a [u8],
+}
+
+impl<'a> Thumbnail<'a> {
+    /// 1 for JFIF (JPEG) data, 0 for raw RGB.
+    pub fn format(&self) -> u32 {
+        self.format
+    }
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+    pub fn bits_per_pixel(&self) -> u16 {
+        self.bits_per_pixel
+    }
+    /// The image, a JFIF file for the JPEG format. Resource 1033 stores its pixels as BGR rather than RGB.
+    pub fn data(&self) -> &'a [u8] {
+        self.data
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PrintFlags {
+    labels: bool,
+    crop_marks: bool,
+    color_bars: bool,
+    registration_marks: bool,
+    negative: bool,
+    flip: bool,
+    interpolate: bool,
+    caption: bool,
+    print_flags: bool,
+}
+
+impl PrintFlags {
+    pub fn labels(&self) -> bool {
+        self.labels
+    }
+    pub fn crop_marks(&self) -> bool {
+        self.crop_marks
+    }
+    pub fn color_bars(&self) -> bool {
+        self.color_bars
+    }
+    pub fn registration_marks(&self) -> bool {
+        self.registration_marks
+    }
+    pub fn negative(&self) -> bool {
+        self.negative
+    }
+    pub fn flip(&self) -> bool {
+        self.flip
+    }
+    pub fn interpolate(&self) -> bool {
+        self.interpolate
+    }
+    pub fn caption(&self) -> bool {
+        self.caption
+    }
+    pub fn print_flags(&self) -> bool {
+        self.print_flags
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ResolutionInfo {
+    horizontal_resolution: f64,
+    horizontal_resolution_unit: u16,
+    width_unit: u16,
+    vertical_resolution: f64,
+    vertical_resolution_unit: u16,
+    height_unit: u16,
+}
+
+impl ResolutionInfo {
+    /// Pixels per inch or per centimeter, depending on [`horizontal_resolution_unit`](Self::horizontal_resolution_unit).
+    pub fn horizontal_resolution(&self) -> f64 {
+        self.horizontal_resolution
+    }
+    /// 1 for pixels per inch, 2 for pixels per centimeter.
+    pub fn horizontal_resolution_unit(&self) -> u16 {
+        self.horizontal_resolution_unit
+    }
+    /// The unit the width is displayed in: 1 inches, 2 centimeters, 3 points, 4 picas, 5 columns.
+    pub fn width_unit(&self) -> u16 {
+        self.width_unit
+    }
+    pub fn vertical_resolution(&self) -> f64 {
+        self.vertical_resolution
+    }
+    pub fn vertical_resolution_unit(&self) -> u16 {
+        self.vertical_resolution_unit
+    }
+    pub fn height_unit(&self) -> u16 {
+        self.height_unit
+    }
+    /// The [`horizontal_resolution`](Self::horizontal_resolution) in pixels per inch whichever unit it is stored in.
+    pub fn pixels_per_inch(&self) -> f64 {
+        match self.horizontal_resolution_unit {
+            2 => self.horizontal_resolution * 2.54,
+            _ => self.horizontal_resolution,
+        }
+    }
+}
+
+/// The version info resource (1057), written when the document is saved with "maximize compatibility" or without it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VersionInfo {
+    version: u32,
+    has_real_merged_data: bool,
+    writer_name: String,
+    reader_name: String,
+    file_version: u32,
+}
+
+impl VersionInfo {
+    pub fn version(&self) -> u32 {
+        self.version
+    }
+    /// Whether the image data holds the merged layers. When cleared the document was saved without "maximize compatibility",
+    /// and the image data is only a placeholder.
+    pub fn has_real_merged_data(&self) -> bool {
+        self.has_real_merged_data
+    }
+    /// The application that wrote the document, such as `Adobe Photoshop`.
+    pub fn writer_name(&self) -> &str {
+        &self.writer_name
+    }
+    /// The application that can read the document.
+    pub fn reader_name(&self) -> &str {
+        &self.reader_name
+    }
+    pub fn file_version(&self) -> u32 {
+        self.file_version
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GuideDirection {
+    Vertical,
+    Horizontal,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Guide {
+    location: f64,
+    direction: GuideDirection,
+}
+
+impl Guide {
+    /// Position in pixels, the x coordinate of a vertical guide or the y coordinate of a horizontal one.
+    pub fn location(&self) -> f64 {
+        self.location
+    }
+    pub fn direction(&self) -> GuideDirection {
+        self.direction
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct GridAndGuides {
+    version: u32,
+    grid_cycle: Option<(u32, u32)>,
+    guides: Vec<Guide>,
+}
+
+impl GridAndGuides {
+    /// Version of the resource layout, 1 for the documented one.
+    pub fn version(&self) -> u32 {
+        self.version
+    }
+    /// Horizontal and vertical grid cycle, `None` when the resource was written without them.
+    pub fn grid_cycle(&self) -> Option<(u32, u32)> {
+        self.grid_cycle
+    }
+    pub fn guides(&self) -> &[Guide] {
+        &self.guides
+    }
+}
+
+pub(crate) fn parse_image_resources<'a>(input: &'a [u8], options: &ParseOptions) -> IResult<&'a [u8], ImageResources<'a>> {
     let (input, len) = be_u32(input)?;
+    let (follow, mut blocks_input) = take(len)(input)?;
     let mut resources = Vec::new();
-    let mut blocks_input = &input[..len as usize];
     while !blocks_input.is_empty() {
-        let (input, block) = parse_image_resource_block(blocks_input)?;
+        let (input, block) = parse_image_resource_block(blocks_input, options)?;
         resources.push(block);
         blocks_input = input;
     }
-    Ok((&input[len as usize..], ImageResources(resources)))
+    Ok((follow, ImageResources(resources)))
 }
 
-fn parse_image_resource_block(input: &[u8]) -> IResult<&[u8], ImageResourceBlock> {
-    let (input, _) = tag(b"8BIM")(input)?;
+fn parse_image_resource_block<'a>(input: &'a [u8], options: &ParseOptions) -> IResult<&'a [u8], ImageResourceBlock<'a>> {
+    let (input, _) = signature(input, &[BIM], options)?;
     let (input, resource_id) = be_u16(input)?;
     let (input, name_len) = be_u8(input)?;
-    let name = &input[..name_len as usize];
-    let input = &input[name_len as usize | 1..];
+    // the name is padded to make its length byte and itself an even size
+    let (input, name) = take(name_len)(input)?;
+    let (input, _) = take(!name_len as usize & 1)(input)?;
     let (input, data_len) = be_u32(input)?;
+    let (input, resource_data) = take(data_len)(input)?;
+    // the data is padded to an even size too, a missing pad at the end of the section is tolerated
+    let input = input.get(data_len as usize & 1..).unwrap_or(input);
     Ok((
-        &input[((data_len + 1) & !1) as usize..],
+        input,
         ImageResourceBlock {
             resource_id,
             name: Cow::Borrowed(name),
-            resource_data: Cow::Borrowed(&input[..data_len as usize]),
+            resource_data: Cow::Borrowed(resource_data),
+        },
+    ))
+}
+
+fn parse_url_list(input: &[u8]) -> IResult<&[u8], Vec<(u32, String)>> {
+    let (mut input, count) = be_u32(input)?;
+    let mut urls = Vec::new();
+    for _ in 0..count {
+        let (i, _) = be_u32(input)?;
+        let (i, id) = be_u32(i)?;
+        let (i, url) = parse_unicode_string(i)?;
+        urls.push((id, url));
+        input = i;
+    }
+    Ok((input, urls))
+}
+
+/// A Pascal string.
+fn parse_caption(data: &[u8]) -> Option<String> {
+    let caption = data.get(1..1 + *data.first()? as usize)?;
+    Some(String::from_utf8_lossy(caption).into_owned())
+}
+
+fn parse_print_flags(data: &[u8]) -> Option<PrintFlags> {
+    let flags = data.get(..9)?;
+    let flag = |index: usize| flags[index] != 0;
+    Some(PrintFlags {
+        labels: flag(0),
+        crop_marks: flag(1),
+        color_bars: flag(2),
+        registration_marks: flag(3),
+        negative: flag(4),
+        flip: flag(5),
+        interpolate: flag(6),
+        caption: flag(7),
+        print_flags: flag(8),
+    })
+}
+
+fn parse_thumbnail(input: &[u8]) -> IResult<&[u8], Thumbnail<'_>> {
+    let (input, format) = be_u32(input)?;
+    let (input, width) = be_u32(input)?;
+    let (input, height) = be_u32(input)?;
+    // the padded row size, the total size and the compressed size
+    let (input, _) = take(12usize)(input)?;
+    let (input, bits_per_pixel) = be_u16(input)?;
+    let (data, _planes) = be_u16(input)?;
+    Ok((&data[data.len()..], Thumbnail { format, width, height, bits_per_pixel, data }))
+}
+
+fn parse_resolution_info(input: &[u8]) -> IResult<&[u8], ResolutionInfo> {
+    let (input, horizontal_resolution) = be_u32(input)?;
+    let (input, horizontal_resolution_unit) = be_u16(input)?;
+    let (input, width_unit) = be_u16(input)?;
+    let (input, vertical_resolution) = be_u32(input)?;
+    let (input, vertical_resolution_unit) = be_u16(input)?;
+    let (input, height_unit) = be_u16(input)?;
+    Ok((
+        input,
+        ResolutionInfo {
+            horizontal_resolution: fixed_16_16(horizontal_resolution),
+            horizontal_resolution_unit,
+            width_unit,
+            vertical_resolution: fixed_16_16(vertical_resolution),
+            vertical_resolution_unit,
+            height_unit,
         },
     ))
 }
+
+fn parse_version_info(input: &[u8]) -> IResult<&[u8], VersionInfo> {
+    let (input, version) = be_u32(input)?;
+    let (input, has_real_merged_data) = be_u8(input)?;
+    let (input, writer_name) = parse_unicode_string(input)?;
+    let (input, reader_name) = parse_unicode_string(input)?;
+    let (input, file_version) = be_u32(input)?;
+    Ok((input, VersionInfo { version, has_real_merged_data: has_real_merged_data != 0, writer_name, reader_name, file_version }))
+}
+
+/// Version 1 stores the grid cycle followed by 5-byte guide records (location in 1/32 pixels, direction).
+/// Writers of other versions drop the grid cycle or pad the guide records, so the layout is chosen by whichever matches the resource length.
+fn parse_grid_and_guides(input: &[u8]) -> IResult<&[u8], GridAndGuides> {
+    let (input, version) = be_u32(input)?;
+    // whether `data` is a guide count followed by exactly that many records of `record_len` bytes
+    let fits = |data: &[u8], record_len: usize| match data.get(..4) {
+        Some(count) => Some(data.len() - 4) == (u32::from_be_bytes(count.try_into().unwrap()) as usize).checked_mul(record_len),
+        None => false,
+    };
+    let with_grid_cycle = version == 1 || !fits(input, 5) && input.get(8..).is_some_and(|data| fits(data, 5));
+    let (input, grid_cycle, record_len) = if with_grid_cycle {
+        let (input, horizontal) = be_u32(input)?;
+        let (input, vertical) = be_u32(input)?;
+        (input, Some((horizontal, vertical)), 5)
+    } else {
+        let record_len = (5..=16).find(|&len| fits(input, len)).unwrap_or(5);
+        (input, None, record_len)
+    };
+    let (mut input, count) = be_u32(input)?;
+    let mut guides = Vec::new();
+    for _ in 0..count {
+        let (i, record) = take(record_len)(input)?;
+        let (record, location) = be_i32(record)?;
+        let (_, direction) = be_u8(record)?;
+        let direction = if direction == 0 { GuideDirection::Vertical } else { GuideDirection::Horizontal };
+        guides.push(Guide { location: fixed_27_5(location), direction });
+        input = i;
+    }
+    Ok((input, GridAndGuides { version, grid_cycle, guides }))
+}
@@ -0,0 +1,70 @@
+use crate::error::PsdError;
+use crate::layer_info::ImageCompression;
+use crate::plane_len;
+
+/// Inflates the zlib stream of data stored with `compression`, stopping at `limit + 1` bytes.
+/// Longer output is cut off there, so that a small stream inflating to gigabytes is found out without holding them.
+#[cfg(feature = "zip")]
+pub(crate) fn inflate(data: &[u8], _compression: ImageCompression, limit: usize) -> Result<Vec<u8>, PsdError> {
+    use std::io::Read;
+
+    let mut inflated = Vec::new();
+    flate2::read::ZlibDecoder::new(data).take(limit as u64 + 1).read_to_end(&mut inflated).map_err(PsdError::InvalidZipData)?;
+    Ok(inflated)
+}
+
+#[cfg(not(feature = "zip"))]
+pub(crate) fn inflate(_data: &[u8], compression: ImageCompression, _limit: usize) -> Result<Vec<u8>, PsdError> {
+    Err(PsdError::UnsupportedCompression(compression))
+}
+
+/// Decompresses zip compressed data of `planes` planes of `width` x `height` into the layout of the RLE path, one row after another.
+pub(crate) fn decompress_zip(data: &[u8], compression: ImageCompression, width: u32, height: u32, depth: u16, planes: usize) -> Result<Vec<u8>, PsdError> {
+    let row_len = plane_len(width, 1, depth, 1)?;
+    let expected = plane_len(width, height, depth, planes)?;
+    let mut inflated = inflate(data, compression, expected)?;
+    if inflated.len() != expected {
+        return Err(PsdError::ZipSizeMismatch { expected, actual: inflated.len() });
+    }
+    if compression == ImageCompression::ZipWithPrediction && row_len > 0 {
+        let mut planar = Vec::new();
+        for row in inflated.chunks_exact_mut(row_len) {
+            undo_prediction(row, width as usize, depth, &mut planar);
+        }
+    }
+    Ok(inflated)
+}
+
+/// Undoes the horizontal delta of a row of `ZipWithPrediction` data, each sample having been stored as the difference to the one before it.
+///
+/// 8 and 16-bit rows are differences of whole samples. A 32-bit row first has its samples split into bytes, all the first bytes of the row
+/// coming first, and the differences are then taken byte by byte over the whole row, so the sum runs over bytes rather than floats
+/// before the bytes are put back together. 1-bit data isn't predicted. `planar` is scratch space for the 32-bit case.
+fn undo_prediction(row: &mut [u8], width: usize, depth: u16, planar: &mut Vec<u8>) {
+    match depth {
+        1 => {}
+        16 => {
+            for i in 1..row.len() / 2 {
+                let sample = u16::from_be_bytes([row[i * 2], row[i * 2 + 1]]).wrapping_add(u16::from_be_bytes([row[i * 2 - 2], row[i * 2 - 1]]));
+                row[i * 2..i * 2 + 2].copy_from_slice(&sample.to_be_bytes());
+            }
+        }
+        32 => {
+            for i in 1..row.len() {
+                row[i] = row[i].wrapping_add(row[i - 1]);
+            }
+            planar.clear();
+            planar.extend_from_slice(row);
+            for (x, sample) in row.chunks_exact_mut(4).enumerate() {
+                for (byte, value) in sample.iter_mut().enumerate() {
+                    *value = planar[byte * width + x];
+                }
+            }
+        }
+        _ => {
+            for i in 1..row.len() {
+                row[i] = row[i].wrapping_add(row[i - 1]);
+            }
+        }
+    }
+}
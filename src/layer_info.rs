@@ -1,41 +1,165 @@
 use std::borrow::Cow;
-use std::convert::TryInto;
+use std::convert::{TryFrom, TryInto};
+use std::path::Path;
 
-use nom::branch::alt;
-use nom::bytes::complete::{tag, take};
+use nom::bytes::complete::take;
 use nom::combinator::map_res;
-use nom::number::complete::{be_i16, be_i32, be_u16, be_u32, be_u8};
+use nom::error::{Error, ErrorKind};
+use nom::number::complete::{be_f64, be_i16, be_i32, be_u16, be_u32, be_u8};
 use nom::IResult;
 use once_cell::sync::OnceCell;
 
-#[derive(Debug, Eq, PartialEq)]
+use crate::adjustment::{parse_color_lookup, AdjustmentLayer};
+use crate::descriptor::{parse_versioned_descriptor, Descriptor};
+use crate::effects::{parse_layer_effects, LayerEffects};
+use crate::engine_data::{parse_engine_data, EngineValue};
+use crate::error::PsdError;
+use crate::fill::{parse_gradient_fill, Gradient};
+use crate::header::{parse_length, PsdHeader, Version};
+use crate::inflate::decompress_zip;
+use crate::linked::{parse_linked_files, LinkedFile};
+use crate::path::{parse_vector_mask, vector_shapes, VectorMask, VectorShape};
+use crate::pattern::{parse_patterns, Pattern};
+use crate::rect::Rect;
+use crate::signature::{find_next_signature, signature, B64, BIM};
+use crate::strings::parse_unicode_string;
+use crate::text::{parse_type_tool, TextLayer};
+use crate::{owned_len, plane_len, LayerId, ParseOptions};
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct LayerAndMaskInformation<'a> {
     layer_info: Vec<LayerTreeNode<'a>>,
+    merged_transparency: bool,
+    #[cfg_attr(feature = "serde", serde(serialize_with = "crate::serialize::len"))]
     global_layer_mask_info: Cow<'a, [u8]>,
+    #[cfg_attr(feature = "serde", serde(serialize_with = "crate::serialize::len"))]
     additional_layer_information: Cow<'a, [u8]>,
+    global_additional_info: Vec<AdditionalLayerInformation<'a>>,
+    merged_alpha: Option<ChannelInfo<'a>>,
 }
 
 impl<'a> LayerAndMaskInformation<'a> {
+    /// Layers arranged as a tree, top-most first. Group boundary records ([`LayerRecord::is_group_boundary`]) only delimit the groups
+    /// and are not included, a group appears as a single [`LayerTreeNode::Node`] holding its folder record.
     pub fn layer_info(&self) -> &[LayerTreeNode<'a>] {
         &self.layer_info
     }
+    /// Every record of [`layer_info`](Self::layer_info) with how many groups it is nested in, top-most first and each folder record before its contents,
+    /// the same order as [`Psd::layers_flat`](crate::Psd::layers_flat) with the groups included.
+    pub fn iter_layers(&self) -> impl Iterator<Item = (&LayerRecord<'a>, usize)> {
+        fn walk<'t, 'a>(nodes: &'t [LayerTreeNode<'a>], depth: usize, layers: &mut Vec<(&'t LayerRecord<'a>, usize)>) {
+            for node in nodes {
+                match node {
+                    LayerTreeNode::Leaf(record) => layers.push((record, depth)),
+                    LayerTreeNode::Node { folder, children } => {
+                        layers.push((folder, depth));
+                        walk(children, depth + 1, layers);
+                    }
+                }
+            }
+        }
+        let mut layers = Vec::new();
+        walk(&self.layer_info, 0, &mut layers);
+        layers.into_iter()
+    }
+    /// The first layer or group, in the order of [`iter_layers`](Self::iter_layers), whose [`unicode_name`](LayerRecord::unicode_name) is `name`.
+    pub fn find_layer(&self, name: &str) -> Option<&LayerRecord<'a>> {
+        self.iter_layers().map(|(record, _)| record).find(|record| record.unicode_name() == name)
+    }
+    /// Whether the first alpha channel of the merged image holds its transparency, recorded as a negative layer count.
+    pub fn merged_transparency(&self) -> bool {
+        self.merged_transparency
+    }
     pub fn global_layer_mask_info(&self) -> &[u8] {
         &self.global_layer_mask_info
     }
     pub fn additional_layer_information(&self) -> &[u8] {
         &self.additional_layer_information
     }
-    pub(crate) fn into_static(self) -> LayerAndMaskInformation<'static> {
-        let LayerAndMaskInformation { layer_info, global_layer_mask_info, additional_layer_information } = self;
+    /// The blocks of [`additional_layer_information`](Self::additional_layer_information), which apply to the document rather than to a layer.
+    /// The `Lr16`/`Lr32` block holding the layers of a deep document isn't among them, its layers are the ones of [`layer_info`](Self::layer_info).
+    pub fn global_additional_info(&self) -> &[AdditionalLayerInformation<'a>] {
+        &self.global_additional_info
+    }
+    /// The decompressed alpha of the merged image from the `Alph` block, one row after another like the image data channels.
+    /// `None` when the document has no such block.
+    ///
+    /// Panics when the block can't be decompressed, see [`try_merged_alpha`](Self::try_merged_alpha).
+    pub fn merged_alpha(&self) -> Option<&[u8]> {
+        self.merged_alpha.as_ref().map(ChannelInfo::raw_data)
+    }
+    /// [`merged_alpha`](Self::merged_alpha), failing for a zip compressed block without the `zip` feature, or when it doesn't decompress to the size of the document.
+    pub fn try_merged_alpha(&self) -> Result<Option<&[u8]>, PsdError> {
+        self.merged_alpha.as_ref().map(ChannelInfo::try_raw_data).transpose()
+    }
+    /// The pattern with the unique id `id` among the patterns of the document, as referenced by the `Idnt` of a pattern fill or overlay.
+    pub fn pattern_by_id(&self, id: &str) -> Option<&Pattern<'a>> {
+        self.global_additional_info
+            .iter()
+            .filter_map(|info| match info {
+                AdditionalLayerInformation::Patterns(patterns) => Some(patterns),
+                _ => None,
+            })
+            .flatten()
+            .find(|pattern| pattern.id() == id)
+    }
+    /// The source with the unique id `id` among the linked files of the document, as referenced by the `Idnt` of a placed layer.
+    pub fn linked_file_by_id(&self, id: &str) -> Option<&LinkedFile<'a>> {
+        self.global_additional_info
+            .iter()
+            .filter_map(|info| match info {
+                AdditionalLayerInformation::LinkedFiles(files) => Some(files),
+                _ => None,
+            })
+            .flatten()
+            .find(|file| file.unique_id() == id)
+    }
+    /// See `Psd::memory_footprint`.
+    pub(crate) fn memory_footprint(&self) -> usize {
+        fn nodes_footprint(nodes: &[LayerTreeNode]) -> usize {
+            nodes
+                .iter()
+                .map(|node| match node {
+                    LayerTreeNode::Leaf(record) => record.memory_footprint(),
+                    LayerTreeNode::Node { folder, children } => folder.memory_footprint() + nodes_footprint(children),
+                })
+                .sum()
+        }
+        nodes_footprint(&self.layer_info)
+            + owned_len(&self.global_layer_mask_info)
+            + owned_len(&self.additional_layer_information)
+            + self.global_additional_info.iter().map(AdditionalLayerInformation::memory_footprint).sum::<usize>()
+            + self.merged_alpha.as_ref().map_or(0, ChannelInfo::memory_footprint)
+    }
+    /// See `Psd::structurally_eq`. The stored global blocks are compared through their parsed form.
+    pub(crate) fn structurally_eq(&self, other: &LayerAndMaskInformation) -> bool {
+        let channel_eq = |a: &Option<ChannelInfo>, b: &Option<ChannelInfo>| match (a, b) {
+            (Some(a), Some(b)) => a.structurally_eq(b),
+            (a, b) => a.is_none() && b.is_none(),
+        };
+        nodes_structurally_eq(&self.layer_info, &other.layer_info)
+            && self.merged_transparency == other.merged_transparency
+            && self.global_layer_mask_info == other.global_layer_mask_info
+            && additional_info_structurally_eq(&self.global_additional_info, &other.global_additional_info)
+            && channel_eq(&self.merged_alpha, &other.merged_alpha)
+    }
+    /// See `ImageData::into_static` for `decode`.
+    pub(crate) fn into_static(self, decode: bool) -> LayerAndMaskInformation<'static> {
+        let LayerAndMaskInformation { layer_info, merged_transparency, global_layer_mask_info, additional_layer_information, global_additional_info, merged_alpha } = self;
         LayerAndMaskInformation {
-            layer_info: layer_info.into_iter().map(LayerTreeNode::into_static).collect(),
+            layer_info: layer_info.into_iter().map(|node| node.into_static(decode)).collect(),
+            merged_transparency,
             global_layer_mask_info: Cow::Owned(global_layer_mask_info.into_owned()),
             additional_layer_information: Cow::Owned(additional_layer_information.into_owned()),
+            global_additional_info: global_additional_info.into_iter().map(|info| info.into_static(decode)).collect(),
+            merged_alpha: merged_alpha.map(|channel| channel.into_static(decode)),
         }
     }
 }
 
-#[derive(Debug, Eq, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct LayerRecord<'a> {
     layer_top: i32,
     layer_left: i32,
@@ -50,9 +174,16 @@ pub struct LayerRecord<'a> {
     clipping: Clipping,
     flags: LayerRecordFlags,
     layer_mask_data: Option<LayerMaskData>,
+    #[cfg_attr(feature = "serde", serde(serialize_with = "crate::serialize::len"))]
     layer_blending_ranges_data: Cow<'a, [u8]>,
+    #[cfg_attr(feature = "serde", serde(serialize_with = "crate::serialize::text"))]
     layer_name: Cow<'a, [u8]>,
     additional_layer_info: Vec<AdditionalLayerInformation<'a>>,
+    #[cfg_attr(feature = "serde", serde(serialize_with = "crate::serialize::len"))]
+    extra_data: Cow<'a, [u8]>,
+    /// Where the additional layer information starts in `extra_data`, after the mask data, blending ranges and name.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    additional_info_offset: usize,
 }
 
 impl<'a> LayerRecord<'a> {
@@ -71,6 +202,32 @@ impl<'a> LayerRecord<'a> {
     pub fn channel_info(&self) -> &[ChannelInfo<'a>] {
         &self.channel_info
     }
+    /// The color channels (id 0 and up) in channel order.
+    pub fn color_channels(&self) -> impl Iterator<Item = &ChannelInfo<'a>> {
+        self.channel_info.iter().filter(|channel| channel.channel_id >= 0)
+    }
+    /// The channel with the id `id`, including the transparency mask (-1) and the user supplied layer masks (-2 and -3)
+    /// that aren't in [`channel_info`](Self::channel_info). `None` when the layer has no such channel, as for a layer without transparency.
+    pub fn channel(&self, id: i16) -> Option<&ChannelInfo<'a>> {
+        match id {
+            -1 => self.transparency_mask(),
+            -2 => self.user_supplied_layer_mask(),
+            -3 => self.real_user_supplied_layer_mask(),
+            _ => self.channel_info.iter().find(|channel| channel.channel_id == id),
+        }
+    }
+    /// The number of channels of the layer, the transparency mask and the user supplied layer masks included, as in the layer record.
+    pub fn channel_count(&self) -> usize {
+        self.all_channels().count()
+    }
+    /// The [`channel_data_length`](ChannelInfo::channel_data_length) of all the channels summed up, the bytes the layer takes in the file
+    /// before decompressing it.
+    pub fn total_compressed_bytes(&self) -> u64 {
+        self.all_channels().map(ChannelInfo::channel_data_length).sum()
+    }
+    fn all_channels(&self) -> impl Iterator<Item = &ChannelInfo<'a>> {
+        self.channel_info.iter().chain(&self.transparency_mask).chain(&self.user_supplied_layer_mask).chain(&self.real_user_supplied_layer_mask)
+    }
     pub fn transparency_mask(&self) -> Option<&ChannelInfo<'a>> {
         self.transparency_mask.as_ref()
     }
@@ -92,6 +249,11 @@ impl<'a> LayerRecord<'a> {
     pub fn flags(&self) -> LayerRecordFlags {
         self.flags
     }
+    /// Whether the eye of the layer is on in the layers panel. A layer inside a hidden group can still be visible on its own.
+    pub fn is_visible(&self) -> bool {
+        // despite its name, the flag is set for hidden layers
+        !self.flags.contains(LayerRecordFlags::VISIBLE)
+    }
     pub fn layer_mask_data(&self) -> Option<&LayerMaskData> {
         self.layer_mask_data.as_ref()
     }
@@ -101,10 +263,214 @@ impl<'a> LayerRecord<'a> {
     pub fn layer_name(&self) -> &[u8] {
         &self.layer_name
     }
-    pub fn additional_layer_info(&self) -> &[AdditionalLayerInformation] {
+    pub fn additional_layer_info(&self) -> &[AdditionalLayerInformation<'a>] {
         &self.additional_layer_info
     }
-    fn into_static(self) -> LayerRecord<'static> {
+    /// The name of the layer from `luni`, falling back to [`layer_name`](Self::layer_name) for layers without one.
+    /// The fallback is read as UTF-8, or as Latin-1 when it isn't valid UTF-8, so characters outside ASCII in the legacy name (stored in the encoding
+    /// of the system that wrote it) may not come out right.
+    pub fn unicode_name(&self) -> Cow<'_, str> {
+        let name = self.additional_layer_info.iter().find_map(|info| match info {
+            AdditionalLayerInformation::UnicodeName(name) => Some(name),
+            _ => None,
+        });
+        match name {
+            Some(name) => Cow::Borrowed(name),
+            None => match std::str::from_utf8(&self.layer_name) {
+                Ok(name) => Cow::Borrowed(name),
+                Err(_) => Cow::Owned(self.layer_name.iter().map(|&c| c as char).collect()),
+            },
+        }
+    }
+    /// The id from `lyid`, which only very old writers leave out.
+    pub fn layer_id(&self) -> Option<LayerId> {
+        self.additional_layer_info.iter().find_map(|info| match info {
+            AdditionalLayerInformation::LayerId(id) => Some(*id),
+            _ => None,
+        })
+    }
+    /// The color label from `lclr`, `None` for layers without the block. An untagged layer with the block gives `Some(LayerColor::None)`.
+    pub fn color_label(&self) -> Option<LayerColor> {
+        self.additional_layer_info.iter().find_map(|info| match info {
+            AdditionalLayerInformation::ColorLabel(color) => Some(*color),
+            _ => None,
+        })
+    }
+    /// The items with a string value of the custom metadata, the `cust` item of the metadata setting (`shmd`), in the order they are stored.
+    /// Items holding other values, such as the time the layer was last edited, are left out.
+    pub fn custom_metadata(&self) -> Vec<(String, String)> {
+        let items = self.additional_layer_info.iter().filter_map(|info| match info {
+            AdditionalLayerInformation::Metadata(items) => Some(items),
+            _ => None,
+        });
+        items
+            .flatten()
+            .filter(|item| item.key() == b"cust")
+            .filter_map(MetadataItem::descriptor)
+            .flat_map(Descriptor::into_items)
+            .filter_map(|(key, value)| Some((key, value.as_str()?.to_string())))
+            .collect()
+    }
+    /// The type of the section divider (`lsct`), which tells a folder record apart and whether the group is open in the layers panel.
+    pub fn section_divider_type(&self) -> Option<SectionDividerType> {
+        self.additional_layer_info.iter().find_map(|info| match info {
+            AdditionalLayerInformation::SectionDivider { section_divider_type, .. } => Some(*section_divider_type),
+            _ => None,
+        })
+    }
+    /// The blend mode key stored in the section divider (`lsct`) of a folder record.
+    /// This can differ from [`blend_mode`](Self::blend_mode) and is the one that applies to the group as a whole.
+    pub fn group_blend_mode(&self) -> Option<BlendMode> {
+        self.additional_layer_info.iter().find_map(|info| match info {
+            AdditionalLayerInformation::SectionDivider { key, .. } => *key,
+            _ => None,
+        })
+    }
+    /// The vector mask, from `vsms` when the layer has it and from `vmsk` otherwise.
+    /// `vogk` only holds the parameters of live shapes, not a path, so it isn't used here, see [`vector_shapes`](Self::vector_shapes).
+    pub fn vector_mask(&self) -> Option<&VectorMask> {
+        let find = |stroke: bool| {
+            self.additional_layer_info.iter().find_map(|info| match info {
+                AdditionalLayerInformation::VectorStrokeMask(mask) if stroke => Some(mask),
+                AdditionalLayerInformation::VectorMask(mask) if !stroke => Some(mask),
+                _ => None,
+            })
+        };
+        find(true).or_else(|| find(false))
+    }
+    /// The shapes of a shape layer with their parameters, rectangles, rounded rectangles and ellipses from the live shape properties (`vogk`)
+    /// and anything else as the path of the [`vector_mask`](Self::vector_mask). Empty for layers without either.
+    pub fn vector_shapes(&self) -> Vec<VectorShape> {
+        let origination = self.additional_layer_info.iter().find_map(|info| match info {
+            AdditionalLayerInformation::VectorOrigination(descriptor) => Some(descriptor),
+            _ => None,
+        });
+        vector_shapes(origination, self.vector_mask())
+    }
+    /// The unique id of the source of a smart object layer, see [`LayerAndMaskInformation::linked_file_by_id`].
+    pub fn smart_object_id(&self) -> Option<&str> {
+        self.additional_layer_info.iter().find_map(|info| match info {
+            AdditionalLayerInformation::PlacedLayer(descriptor) => descriptor.get("Idnt")?.as_str(),
+            _ => None,
+        })
+    }
+    /// Writes the source file of this smart object layer to `path`, which is a complete file such as a PNG, a JPEG or another PSD.
+    /// Returns `false` without writing anything when the source is linked rather than embedded, or this isn't a smart object.
+    ///
+    /// The sources are stored in the global `lnkD` block rather than in the layer, so this looks them up in `information`, the one of the document of the layer.
+    pub fn export_smart_object_source<P: AsRef<Path>>(&self, information: &LayerAndMaskInformation, path: P) -> Result<bool, PsdError> {
+        match self.smart_object_id().and_then(|id| information.linked_file_by_id(id)).and_then(|file| file.data()) {
+            Some(data) => {
+                std::fs::write(path, data)?;
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+    /// The layer effects, from `lmfx` when the layer has it and from `lfx2` otherwise.
+    pub fn effects(&self) -> Option<&LayerEffects<'a>> {
+        let find = |multiple: bool| {
+            self.additional_layer_info.iter().find_map(|info| match info {
+                AdditionalLayerInformation::MultipleEffects(effects) if multiple => Some(effects),
+                AdditionalLayerInformation::Effects(effects) if !multiple => Some(effects),
+                _ => None,
+            })
+        };
+        find(true).or_else(|| find(false))
+    }
+    /// The text of a text layer.
+    pub fn text(&self) -> Option<&TextLayer> {
+        self.additional_layer_info.iter().find_map(|info| match info {
+            AdditionalLayerInformation::TypeTool(text) => Some(text),
+            _ => None,
+        })
+    }
+    /// The settings of an adjustment layer.
+    pub fn adjustment(&self) -> Option<&AdjustmentLayer<'a>> {
+        self.additional_layer_info.iter().find_map(|info| match info {
+            AdditionalLayerInformation::Adjustment(adjustment) => Some(adjustment),
+            _ => None,
+        })
+    }
+    /// Whether this is the hidden `</Layer group>` record closing a group.
+    /// These are only markers for the group structure, [`LayerAndMaskInformation::layer_info`] consumes them and never returns them as layers.
+    pub fn is_group_boundary(&self) -> bool {
+        self.additional_layer_info.iter().any(|info| {
+            matches!(info, AdditionalLayerInformation::SectionDivider { section_divider_type: SectionDividerType::BoundingSectionDivider, .. })
+        })
+    }
+    /// The smallest rectangle holding every pixel of non-zero alpha, relative to the top left corner of the layer.
+    /// A layer without a transparency mask is opaque and keeps its whole rectangle, a fully transparent or empty one gives `None`.
+    /// Fails when the transparency mask can't be decompressed.
+    pub fn content_trim(&self) -> Result<Option<Rect>, PsdError> {
+        let width = rect_extent(self.layer_left, self.layer_right);
+        let height = rect_extent(self.layer_top, self.layer_bottom);
+        let alpha = match &self.transparency_mask {
+            Some(alpha) => alpha,
+            None => return Ok(Some(Rect::from_size(0, 0, width, height)).filter(|rect| !rect.is_empty())),
+        };
+        let depth = alpha.depth as usize;
+        let row_len = alpha.row_len();
+        let data = alpha.try_raw_data()?;
+        let visible = |row: &[u8], x: usize| match depth {
+            1 => row[x / 8] & (0x80 >> (x % 8)) != 0,
+            _ => row[x * depth / 8..(x + 1) * depth / 8].iter().any(|&byte| byte != 0),
+        };
+        let (mut top, mut left, mut bottom, mut right) = (usize::MAX, usize::MAX, 0, 0);
+        for (y, row) in data.chunks_exact(row_len.max(1)).take(alpha.channel_data_height as usize).enumerate() {
+            let Some(first) = (0..alpha.channel_data_width as usize).find(|&x| visible(row, x)) else {
+                continue;
+            };
+            let last = (first..alpha.channel_data_width as usize).rev().find(|&x| visible(row, x)).unwrap_or(first);
+            top = top.min(y);
+            bottom = y + 1;
+            left = left.min(first);
+            right = right.max(last + 1);
+        }
+        if top == usize::MAX {
+            return Ok(None);
+        }
+        Ok(Some(Rect::new(top as i32, left as i32, bottom as i32, right as i32)))
+    }
+    fn memory_footprint(&self) -> usize {
+        self.all_channels().map(ChannelInfo::memory_footprint).sum::<usize>()
+            + owned_len(&self.layer_blending_ranges_data)
+            + owned_len(&self.layer_name)
+            + owned_len(&self.extra_data)
+            + self.additional_layer_info.iter().map(AdditionalLayerInformation::memory_footprint).sum::<usize>()
+    }
+    /// Compares everything but the stored extra data, the channels by their decoded data.
+    fn structurally_eq(&self, other: &LayerRecord) -> bool {
+        let channel_eq = |a: &Option<ChannelInfo>, b: &Option<ChannelInfo>| match (a, b) {
+            (Some(a), Some(b)) => a.structurally_eq(b),
+            (a, b) => a.is_none() && b.is_none(),
+        };
+        (self.layer_top, self.layer_left, self.layer_bottom, self.layer_right) == (other.layer_top, other.layer_left, other.layer_bottom, other.layer_right)
+            && self.channel_info.len() == other.channel_info.len()
+            && self.channel_info.iter().zip(&other.channel_info).all(|(a, b)| a.structurally_eq(b))
+            && channel_eq(&self.transparency_mask, &other.transparency_mask)
+            && channel_eq(&self.user_supplied_layer_mask, &other.user_supplied_layer_mask)
+            && channel_eq(&self.real_user_supplied_layer_mask, &other.real_user_supplied_layer_mask)
+            && (self.blend_mode, self.opacity, self.clipping, self.flags) == (other.blend_mode, other.opacity, other.clipping, other.flags)
+            && self.layer_mask_data == other.layer_mask_data
+            && self.layer_blending_ranges_data == other.layer_blending_ranges_data
+            && self.layer_name == other.layer_name
+            && additional_info_structurally_eq(&self.additional_layer_info, &other.additional_layer_info)
+    }
+    /// The additional layer information blocks exactly as stored in the file, signatures and lengths included, to copy them into another document
+    /// along with the blocks this crate doesn't understand. Borrowed from the input unless the document has been made owned.
+    pub fn additional_info_bytes(&self) -> Cow<'a, [u8]> {
+        let start = self.additional_info_offset;
+        match &self.extra_data {
+            Cow::Borrowed(data) => Cow::Borrowed(&data[start..]),
+            Cow::Owned(data) => Cow::Owned(data[start..].to_vec()),
+        }
+    }
+    /// The extra data fields (mask data, blending ranges, name, additional layer information) as stored in the file.
+    pub(crate) fn extra_data(&self) -> &[u8] {
+        &self.extra_data
+    }
+    fn into_static(self, decode: bool) -> LayerRecord<'static> {
         let LayerRecord {
             layer_top,
             layer_left,
@@ -122,16 +488,18 @@ impl<'a> LayerRecord<'a> {
             layer_blending_ranges_data,
             layer_name,
             additional_layer_info,
+            extra_data,
+            additional_info_offset,
         } = self;
         LayerRecord {
             layer_top,
             layer_left,
             layer_bottom,
             layer_right,
-            channel_info: channel_info.into_iter().map(ChannelInfo::into_static).collect(),
-            transparency_mask: transparency_mask.map(ChannelInfo::into_static),
-            user_supplied_layer_mask: user_supplied_layer_mask.map(ChannelInfo::into_static),
-            real_user_supplied_layer_mask: real_user_supplied_layer_mask.map(ChannelInfo::into_static),
+            channel_info: channel_info.into_iter().map(|channel| channel.into_static(decode)).collect(),
+            transparency_mask: transparency_mask.map(|channel| channel.into_static(decode)),
+            user_supplied_layer_mask: user_supplied_layer_mask.map(|channel| channel.into_static(decode)),
+            real_user_supplied_layer_mask: real_user_supplied_layer_mask.map(|channel| channel.into_static(decode)),
             blend_mode,
             opacity,
             clipping,
@@ -139,12 +507,15 @@ impl<'a> LayerRecord<'a> {
             layer_mask_data,
             layer_blending_ranges_data: Cow::Owned(layer_blending_ranges_data.into_owned()),
             layer_name: Cow::Owned(layer_name.into_owned()),
-            additional_layer_info: additional_layer_info.into_iter().map(AdditionalLayerInformation::into_static).collect(),
+            additional_layer_info: additional_layer_info.into_iter().map(|info| info.into_static(decode)).collect(),
+            extra_data: Cow::Owned(extra_data.into_owned()),
+            additional_info_offset,
         }
     }
 }
 
-#[derive(Debug, Eq, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct LayerMaskData {
     layer_mask_top: i32,
     layer_mask_left: i32,
@@ -152,6 +523,8 @@ pub struct LayerMaskData {
     layer_mask_right: i32,
     default_color: u8,
     flags: LayerMaskFlags,
+    user_mask_adjustments: Option<MaskAdjustments>,
+    vector_mask_adjustments: Option<MaskAdjustments>,
     optional: Option<LayerMaskOptionalData>,
 }
 
@@ -171,15 +544,68 @@ impl LayerMaskData {
     pub fn default_color(&self) -> u8 {
         self.default_color
     }
+    /// Whether the mask rectangle is stored relative to the top left corner of the layer rather than in document coordinates.
+    pub fn position_relative_to_layer(&self) -> bool {
+        self.flags.contains(LayerMaskFlags::POSITION_RELATIVE_TO_LAYER)
+    }
+    /// Whether the mask is turned off, in which case the layer shows as if it had none.
+    pub fn disabled(&self) -> bool {
+        self.flags.contains(LayerMaskFlags::LAYER_MASK_DISABLED)
+    }
+    /// Whether the mask is inverted when blending, hiding the layer where it would show it. Photoshop documents the flag as obsolete,
+    /// but still reads it from older documents.
+    pub fn inverted(&self) -> bool {
+        self.flags.contains(LayerMaskFlags::INVERT_LAYER_MASK_WHEN_BLENDING)
+    }
+    /// The rectangle of the mask as stored, see [`canvas_rect`](Self::canvas_rect) for where it is in the document.
+    pub fn rect(&self) -> Rect {
+        Rect::new(self.layer_mask_top, self.layer_mask_left, self.layer_mask_bottom, self.layer_mask_right)
+    }
+    /// The rectangle of the document the mask covers, resolving a position relative to `layer`.
+    pub fn canvas_rect(&self, layer: &LayerRecord) -> Rect {
+        let rect = self.rect();
+        if self.position_relative_to_layer() {
+            Rect::from_size(layer.layer_left + rect.left(), layer.layer_top + rect.top(), rect.width(), rect.height())
+        } else {
+            rect
+        }
+    }
     pub fn flags(&self) -> LayerMaskFlags {
         self.flags
     }
+    /// Density and feather of the user (pixel) mask, present when they are set to other than the defaults.
+    pub fn user_mask_adjustments(&self) -> Option<MaskAdjustments> {
+        self.user_mask_adjustments
+    }
+    /// Density and feather of the vector mask, present when they are set to other than the defaults.
+    pub fn vector_mask_adjustments(&self) -> Option<MaskAdjustments> {
+        self.vector_mask_adjustments
+    }
     pub fn optional(&self) -> Option<&LayerMaskOptionalData> {
         self.optional.as_ref()
     }
 }
 
-#[derive(Debug, Eq, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct MaskAdjustments {
+    density: f64,
+    feather: f64,
+}
+
+impl MaskAdjustments {
+    /// Strength of the mask in `0.0..=1.0`, `1.0` when not specified.
+    pub fn density(&self) -> f64 {
+        self.density
+    }
+    /// Feather radius in pixels, `0.0` when not specified.
+    pub fn feather(&self) -> f64 {
+        self.feather
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct LayerMaskOptionalData {
     real_flags: LayerMaskFlags,
     real_user_mask_background: u8,
@@ -230,7 +656,36 @@ bitflags::bitflags! {
     }
 }
 
-#[derive(Debug, Eq, PartialEq)]
+bitflags::bitflags! {
+    /// The locks of `lspf`.
+    pub struct ProtectionFlags : u32 {
+        const TRANSPARENCY_LOCKED = 0x0000_0001;
+        const COMPOSITE_LOCKED = 0x0000_0002;
+        const POSITION_LOCKED = 0x0000_0004;
+        const ALL_LOCKED = 0x8000_0000;
+    }
+}
+
+impl ProtectionFlags {
+    /// Whether the transparent pixels are locked.
+    pub fn transparency_locked(&self) -> bool {
+        self.contains(ProtectionFlags::TRANSPARENCY_LOCKED)
+    }
+    /// Whether the pixels are locked against painting.
+    pub fn composite_locked(&self) -> bool {
+        self.contains(ProtectionFlags::COMPOSITE_LOCKED)
+    }
+    pub fn position_locked(&self) -> bool {
+        self.contains(ProtectionFlags::POSITION_LOCKED)
+    }
+    /// Whether the layer is locked entirely, which sets only this bit rather than the ones of the other locks.
+    pub fn all_locked(&self) -> bool {
+        self.contains(ProtectionFlags::ALL_LOCKED)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum SectionDividerType {
     BoundingSectionDivider,
     OpenFolder,
@@ -250,7 +705,82 @@ impl SectionDividerType {
     }
 }
 
-#[derive(Debug, Eq, PartialEq)]
+/// The color a layer is tagged with in the layers panel (`lclr`).
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum LayerColor {
+    None,
+    Red,
+    Orange,
+    Yellow,
+    Green,
+    Blue,
+    Violet,
+    Gray,
+}
+
+impl LayerColor {
+    pub fn from_u16(value: u16) -> Result<Self, u16> {
+        match value {
+            0 => Ok(LayerColor::None),
+            1 => Ok(LayerColor::Red),
+            2 => Ok(LayerColor::Orange),
+            3 => Ok(LayerColor::Yellow),
+            4 => Ok(LayerColor::Green),
+            5 => Ok(LayerColor::Blue),
+            6 => Ok(LayerColor::Violet),
+            7 => Ok(LayerColor::Gray),
+            _ => Err(value),
+        }
+    }
+    /// The name of the color in lowercase, as the layers panel shows it.
+    pub fn name(self) -> &'static str {
+        match self {
+            LayerColor::None => "none",
+            LayerColor::Red => "red",
+            LayerColor::Orange => "orange",
+            LayerColor::Yellow => "yellow",
+            LayerColor::Green => "green",
+            LayerColor::Blue => "blue",
+            LayerColor::Violet => "violet",
+            LayerColor::Gray => "gray",
+        }
+    }
+}
+
+/// An item of the metadata setting (`shmd`), such as `cust` for custom metadata or `mlst` for the layer comps of the layer.
+#[derive(Debug, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct MetadataItem<'a> {
+    #[cfg_attr(feature = "serde", serde(serialize_with = "crate::serialize::text"))]
+    key: [u8; 4],
+    copy_on_sheet_duplication: bool,
+    #[cfg_attr(feature = "serde", serde(serialize_with = "crate::serialize::len"))]
+    data: Cow<'a, [u8]>,
+}
+
+impl<'a> MetadataItem<'a> {
+    pub fn key(&self) -> &[u8; 4] {
+        &self.key
+    }
+    pub fn copy_on_sheet_duplication(&self) -> bool {
+        self.copy_on_sheet_duplication
+    }
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+    /// The data read as a versioned descriptor, which is how the items Photoshop writes store their content.
+    pub fn descriptor(&self) -> Option<Descriptor<'_>> {
+        parse_versioned_descriptor(&self.data).ok().map(|(_, descriptor)| descriptor)
+    }
+    fn into_static(self) -> MetadataItem<'static> {
+        let MetadataItem { key, copy_on_sheet_duplication, data } = self;
+        MetadataItem { key, copy_on_sheet_duplication, data: Cow::Owned(data.into_owned()) }
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum SectionDividerSubType {
     Normal,
     SceneGroup,
@@ -266,29 +796,121 @@ impl SectionDividerSubType {
     }
 }
 
-#[derive(Debug, Eq, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum AdditionalLayerInformation<'a> {
-    SectionDivider { section_divider_type: SectionDividerType, key: Option<BlendMode>, sub_type: Option<SectionDividerSubType> },
-    Unknown { key: Cow<'a, [u8; 4]>, data: Cow<'a, [u8]> },
+    /// `lsct`, or `lsdk` for a nested one. `extra` holds whatever newer versions write after the sub type.
+    SectionDivider {
+        section_divider_type: SectionDividerType,
+        key: Option<BlendMode>,
+        sub_type: Option<SectionDividerSubType>,
+        #[cfg_attr(feature = "serde", serde(serialize_with = "crate::serialize::len"))]
+        extra: Cow<'a, [u8]>,
+    },
+    /// `luni`, the name of the layer in UTF-16, which unlike [`LayerRecord::layer_name`] can hold any character.
+    UnicodeName(String),
+    /// `lyid`, the id of the layer.
+    LayerId(u32),
+    /// `lclr`, the color label of the layer.
+    ColorLabel(LayerColor),
+    /// `tsly`, whether the transparency of the layer shapes its effects and the knockout of the group it is in ("Transparency Shapes Layer").
+    /// When cleared, a knockout cuts through the whole layer rectangle instead of only where the layer has pixels.
+    TransparencyShapesLayer(bool),
+    /// `lspf`, the locks of the layer.
+    ProtectionFlags(ProtectionFlags),
+    /// `shmd`, the metadata setting.
+    Metadata(Vec<MetadataItem<'a>>),
+    /// `fxrp`, the reference point the effects and transforms of the layer are anchored at.
+    ReferencePoint { x: f64, y: f64 },
+    GradientFill(Gradient),
+    /// `vmsk`, the vector mask as older versions write it.
+    VectorMask(VectorMask),
+    /// `vsms`, written instead of or alongside `vmsk` by newer versions.
+    VectorStrokeMask(VectorMask),
+    /// `vogk`, the vector origination data holding the parameters of the live shapes of a shape layer, see [`LayerRecord::vector_shapes`].
+    VectorOrigination(Descriptor<'a>),
+    /// `lfx2`, the layer effects.
+    Effects(LayerEffects<'a>),
+    /// `lmfx`, the layer effects written when some effect is applied more than once.
+    MultipleEffects(LayerEffects<'a>),
+    /// `FMsk`, the color and opacity of a filter mask.
+    FilterMask { color_space: u16, color: [u16; 4], opacity: u16 },
+    /// `TySh`, the text of a text layer.
+    TypeTool(TextLayer),
+    /// `Txt2`, the text engine data of the document, shared by its text layers.
+    TextEngineData(EngineValue),
+    /// The settings of an adjustment layer, for now only the color lookup (`clrL`).
+    Adjustment(AdjustmentLayer<'a>),
+    /// `Patt`, `Pat2` or `Pat3`, the patterns of the document.
+    Patterns(Vec<Pattern<'a>>),
+    /// `SoLd` or `SoLE`, the placement of a smart object, whose `Idnt` is the unique id of its [`LinkedFile`].
+    PlacedLayer(Descriptor<'a>),
+    /// `lnkD`, `lnk2` or `lnk3`, the sources of the smart objects of the document.
+    LinkedFiles(Vec<LinkedFile<'a>>),
+    Unknown {
+        #[cfg_attr(feature = "serde", serde(serialize_with = "crate::serialize::key"))]
+        key: Cow<'a, [u8; 4]>,
+        #[cfg_attr(feature = "serde", serde(serialize_with = "crate::serialize::len"))]
+        data: Cow<'a, [u8]>,
+    },
 }
 
 impl<'a> AdditionalLayerInformation<'a> {
-    fn into_static(self) -> AdditionalLayerInformation<'static> {
+    /// Only the blocks kept as bytes are counted.
+    fn memory_footprint(&self) -> usize {
         match self {
-            AdditionalLayerInformation::SectionDivider { section_divider_type, key, sub_type } => AdditionalLayerInformation::SectionDivider { section_divider_type, key, sub_type },
+            AdditionalLayerInformation::SectionDivider { extra, .. } => owned_len(extra),
+            AdditionalLayerInformation::Adjustment(AdjustmentLayer::ColorLookup { data, .. }) => data.as_ref().map_or(0, owned_len),
+            AdditionalLayerInformation::Patterns(patterns) => patterns.iter().map(Pattern::memory_footprint).sum(),
+            AdditionalLayerInformation::LinkedFiles(files) => files.iter().map(LinkedFile::memory_footprint).sum(),
+            AdditionalLayerInformation::Metadata(items) => items.iter().map(|item| owned_len(&item.data)).sum(),
+            AdditionalLayerInformation::Unknown { data, .. } => owned_len(data),
+            _ => 0,
+        }
+    }
+    fn into_static(self, decode: bool) -> AdditionalLayerInformation<'static> {
+        match self {
+            AdditionalLayerInformation::SectionDivider { section_divider_type, key, sub_type, extra } => {
+                AdditionalLayerInformation::SectionDivider { section_divider_type, key, sub_type, extra: Cow::Owned(extra.into_owned()) }
+            }
+            AdditionalLayerInformation::UnicodeName(name) => AdditionalLayerInformation::UnicodeName(name),
+            AdditionalLayerInformation::LayerId(id) => AdditionalLayerInformation::LayerId(id),
+            AdditionalLayerInformation::TransparencyShapesLayer(shapes) => AdditionalLayerInformation::TransparencyShapesLayer(shapes),
+            AdditionalLayerInformation::ColorLabel(color) => AdditionalLayerInformation::ColorLabel(color),
+            AdditionalLayerInformation::ProtectionFlags(flags) => AdditionalLayerInformation::ProtectionFlags(flags),
+            AdditionalLayerInformation::Metadata(items) => AdditionalLayerInformation::Metadata(items.into_iter().map(MetadataItem::into_static).collect()),
+            AdditionalLayerInformation::ReferencePoint { x, y } => AdditionalLayerInformation::ReferencePoint { x, y },
+            AdditionalLayerInformation::GradientFill(gradient) => AdditionalLayerInformation::GradientFill(gradient),
+            AdditionalLayerInformation::VectorMask(mask) => AdditionalLayerInformation::VectorMask(mask),
+            AdditionalLayerInformation::VectorStrokeMask(mask) => AdditionalLayerInformation::VectorStrokeMask(mask),
+            AdditionalLayerInformation::VectorOrigination(descriptor) => AdditionalLayerInformation::VectorOrigination(descriptor.into_static()),
+            AdditionalLayerInformation::Effects(effects) => AdditionalLayerInformation::Effects(effects.into_static()),
+            AdditionalLayerInformation::MultipleEffects(effects) => AdditionalLayerInformation::MultipleEffects(effects.into_static()),
+            AdditionalLayerInformation::FilterMask { color_space, color, opacity } => AdditionalLayerInformation::FilterMask { color_space, color, opacity },
+            AdditionalLayerInformation::TypeTool(text) => AdditionalLayerInformation::TypeTool(text),
+            AdditionalLayerInformation::TextEngineData(engine_data) => AdditionalLayerInformation::TextEngineData(engine_data),
+            AdditionalLayerInformation::Adjustment(adjustment) => AdditionalLayerInformation::Adjustment(adjustment.into_static()),
+            AdditionalLayerInformation::Patterns(patterns) => AdditionalLayerInformation::Patterns(patterns.into_iter().map(|pattern| pattern.into_static(decode)).collect()),
+            AdditionalLayerInformation::PlacedLayer(descriptor) => AdditionalLayerInformation::PlacedLayer(descriptor.into_static()),
+            AdditionalLayerInformation::LinkedFiles(files) => AdditionalLayerInformation::LinkedFiles(files.into_iter().map(LinkedFile::into_static).collect()),
             AdditionalLayerInformation::Unknown { key, data } => AdditionalLayerInformation::Unknown { key: Cow::Owned(key.into_owned()), data: Cow::Owned(data.into_owned()) },
         }
     }
 }
 
-#[derive(Debug, Eq, PartialEq)]
+#[derive(Debug, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct ChannelInfo<'a> {
     channel_id: i16,
-    channel_data_length: u32,
+    channel_data_length: u64,
     channel_data_width: u32,
     channel_data_height: u32,
+    depth: u16,
+    version: Version,
     compression: ImageCompression,
+    #[cfg_attr(feature = "serde", serde(serialize_with = "crate::serialize::len"))]
     data: Cow<'a, [u8]>,
+    #[cfg_attr(feature = "serde", serde(skip))]
     raw_data: OnceCell<Cow<'a, [u8]>>,
 }
 
@@ -296,66 +918,108 @@ impl<'a> ChannelInfo<'a> {
     pub fn channel_id(&self) -> i16 {
         self.channel_id
     }
-    pub fn channel_data_length(&self) -> u32 {
+    pub fn channel_data_length(&self) -> u64 {
         self.channel_data_length
     }
+    /// Width of the channel in pixels. Mask channels have the size of their mask rectangle rather than of the layer.
+    pub fn width(&self) -> u32 {
+        self.channel_data_width
+    }
+    /// Height of the channel in pixels, see [`width`](Self::width).
+    pub fn height(&self) -> u32 {
+        self.channel_data_height
+    }
+    pub(crate) fn depth(&self) -> u16 {
+        self.depth
+    }
+    /// Whether this is the user supplied layer mask (-2) or the real user supplied layer mask (-3).
+    pub fn is_mask(&self) -> bool {
+        self.channel_id == -2 || self.channel_id == -3
+    }
+    /// Whether this is the transparency mask (-1).
+    pub fn is_alpha(&self) -> bool {
+        self.channel_id == -1
+    }
     pub fn compression(&self) -> ImageCompression {
         self.compression
     }
+    /// The channel data after the 2-byte compression method, still compressed.
     pub fn data(&self) -> &[u8] {
+        self.data.get(2..).unwrap_or(&[])
+    }
+    /// The channel data exactly as stored in the file, including the 2-byte compression method.
+    /// Copying these bytes into another document preserves the channel without recompressing it.
+    pub fn compressed_bytes(&self) -> &[u8] {
         &self.data
     }
+    /// Bytes in one row of [`raw_data`](Self::raw_data), rows of 1-bit channels are padded to whole bytes.
+    fn row_len(&self) -> usize {
+        plane_len(self.channel_data_width, 1, self.depth, 1).unwrap_or(usize::MAX)
+    }
+    /// The decompressed channel, row by row. Samples deeper than 8 bits are big-endian, and a 1-bit channel packs 8 pixels into a byte,
+    /// so a row is `ceil(width * depth / 8)` bytes.
+    ///
+    /// Panics when the data can't be decompressed, see [`try_raw_data`](Self::try_raw_data).
     pub fn raw_data(&self) -> &[u8] {
-        self.raw_data.get_or_init(|| match self.compression {
-            ImageCompression::Raw => self.data.clone(),
+        self.try_raw_data().unwrap_or_else(|error| panic!("{}", error))
+    }
+    /// [`raw_data`](Self::raw_data), failing for zip compressed data without the `zip` feature, or when the data doesn't decompress to the size of the channel.
+    pub fn try_raw_data(&self) -> Result<&[u8], PsdError> {
+        let raw_data = self.raw_data.get_or_try_init(|| match self.compression {
+            ImageCompression::Raw => Ok(match self.data {
+                Cow::Borrowed(data) => Cow::Borrowed(data.get(2..).unwrap_or(&[])),
+                // owned data that isn't decoded yet, from `Psd::clone_owned` or built in memory, can't be borrowed for `'a`
+                Cow::Owned(_) => Cow::Owned(self.data().to_vec()),
+            }),
             ImageCompression::RLE => {
-                let mut result = Vec::with_capacity(self.channel_data_width as usize * self.channel_data_height as usize);
-                let mut data = &self.data[self.channel_data_height as usize * 2..];
-                while !data.is_empty() {
-                    let (&len, follow) = data.split_first().unwrap();
-                    match len as i8 {
-                        len @ 0..=127 => {
-                            let len = len as usize;
-                            result.extend(&follow[..len + 1]);
-                            data = &follow[len + 1..];
-                        }
-                        len @ -127..=-1 => {
-                            for _ in 0..-len as usize + 1 {
-                                result.push(follow[0]);
-                            }
-                            data = &follow[1..];
-                        }
-                        -128 => {
-                            println!("may be error");
-                        }
-                    }
-                }
-                Cow::Owned(result)
+                let len = plane_len(self.channel_data_width, self.channel_data_height, self.depth, 1)?;
+                let data = self.data().get(self.channel_data_height as usize * self.version.byte_count_size()..).unwrap_or(&[]);
+                Ok(Cow::Owned(unpack_bits(data, len)?))
             }
-            ImageCompression::ZipWithoutPrediction | ImageCompression::ZipWithPrediction => {
-                panic!("Zip compression is not supported")
+            compression @ (ImageCompression::ZipWithoutPrediction | ImageCompression::ZipWithPrediction) => {
+                decompress_zip(self.data(), compression, self.channel_data_width, self.channel_data_height, self.depth, 1).map(Cow::Owned)
             }
-        })
+        })?;
+        Ok(raw_data)
     }
-    fn into_static(self) -> ChannelInfo<'static> {
-        let _ = self.raw_data();
+    fn memory_footprint(&self) -> usize {
+        owned_len(&self.data) + self.raw_data.get().map_or(0, owned_len)
+    }
+    /// Compares the decoded data rather than how it is stored, falling back to the stored bytes for data that can't be decoded.
+    fn structurally_eq(&self, other: &ChannelInfo) -> bool {
+        let data_eq = match (self.try_raw_data(), other.try_raw_data()) {
+            (Ok(a), Ok(b)) => a == b,
+            _ => self.data == other.data,
+        };
+        (self.channel_id, self.channel_data_width, self.channel_data_height, self.depth) == (other.channel_id, other.channel_data_width, other.channel_data_height, other.depth) && data_eq
+    }
+    /// With `decode`, data that can't be decoded is kept compressed, failing again when it is used.
+    fn into_static(self, decode: bool) -> ChannelInfo<'static> {
+        if decode {
+            let _ = self.try_raw_data();
+        }
         let ChannelInfo {
             channel_id,
             channel_data_length,
             channel_data_width,
             channel_data_height,
+            depth,
+            version,
             compression,
             data,
             raw_data,
         } = self;
-        let raw_data = raw_data.into_inner().unwrap();
         let raw_data_cell = OnceCell::<Cow<'static, [u8]>>::new();
-        raw_data_cell.set(Cow::Owned(raw_data.into_owned())).unwrap();
+        if let Some(raw_data) = raw_data.into_inner() {
+            raw_data_cell.set(Cow::Owned(raw_data.into_owned())).unwrap();
+        }
         ChannelInfo {
             channel_id,
             channel_data_length,
             channel_data_width,
             channel_data_height,
+            depth,
+            version,
             compression,
             data: Cow::Owned(data.into_owned()),
             raw_data: raw_data_cell,
@@ -364,6 +1028,7 @@ impl<'a> ChannelInfo<'a> {
 }
 
 #[derive(Debug, Clone, Copy, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum BlendMode {
     Passthrough,
     Normal,
@@ -396,6 +1061,38 @@ pub enum BlendMode {
 }
 
 impl BlendMode {
+    pub(crate) fn key(self) -> &'static [u8; 4] {
+        match self {
+            BlendMode::Passthrough => b"pass",
+            BlendMode::Normal => b"norm",
+            BlendMode::Dissolve => b"diss",
+            BlendMode::Darken => b"dark",
+            BlendMode::Multiply => b"mul ",
+            BlendMode::Colorburn => b"idiv",
+            BlendMode::Linearburn => b"lbrn",
+            BlendMode::Darkercolor => b"dkCl",
+            BlendMode::Lighten => b"lite",
+            BlendMode::Screen => b"scrn",
+            BlendMode::Colordodge => b"div ",
+            BlendMode::Lineardodge => b"lddg",
+            BlendMode::Lightercolor => b"lgCl",
+            BlendMode::Overlay => b"over",
+            BlendMode::Softlight => b"sLit",
+            BlendMode::Hardlight => b"hLit",
+            BlendMode::Vividlight => b"vLit",
+            BlendMode::Linearlight => b"lLit",
+            BlendMode::Pinlight => b"pLit",
+            BlendMode::Hardmix => b"hMix",
+            BlendMode::Difference => b"diff",
+            BlendMode::Exclusion => b"smud",
+            BlendMode::Subtract => b"fsub",
+            BlendMode::Divide => b"fdiv",
+            BlendMode::Hue => b"hue ",
+            BlendMode::Saturation => b"sat ",
+            BlendMode::Color => b"colr",
+            BlendMode::Luminosity => b"lum ",
+        }
+    }
     fn try_from(input: &[u8]) -> Result<Self, &[u8]> {
         match input {
             b"pass" => Ok(BlendMode::Passthrough),
@@ -432,12 +1129,19 @@ impl BlendMode {
 }
 
 #[derive(Debug, Clone, Copy, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum Clipping {
     Base,
     NonBase,
 }
 
 impl Clipping {
+    pub(crate) fn to_u8(self) -> u8 {
+        match self {
+            Clipping::Base => 0,
+            Clipping::NonBase => 1,
+        }
+    }
     fn try_from(input: u8) -> Result<Self, u8> {
         match input {
             0 => Ok(Clipping::Base),
@@ -448,6 +1152,7 @@ impl Clipping {
 }
 
 #[derive(Debug, Clone, Copy, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum ImageCompression {
     Raw,
     RLE,
@@ -456,7 +1161,7 @@ pub enum ImageCompression {
 }
 
 impl ImageCompression {
-    pub(crate) fn from_u16(value: u16) -> Result<Self, u16> {
+    pub fn from_u16(value: u16) -> Result<Self, u16> {
         match value {
             0 => Ok(ImageCompression::Raw),
             1 => Ok(ImageCompression::RLE),
@@ -467,7 +1172,14 @@ impl ImageCompression {
     }
 }
 
-#[derive(Debug, Eq, PartialEq)]
+impl TryFrom<u16> for ImageCompression {
+    type Error = u16;
+    fn try_from(value: u16) -> Result<Self, Self::Error> {
+        ImageCompression::from_u16(value)
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
 pub struct ChannelImageData<'a> {
     compression: ImageCompression,
     data: &'a [u8],
@@ -482,61 +1194,255 @@ impl<'a> ChannelImageData<'a> {
     }
 }
 
-#[derive(Debug, Eq, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum LayerTreeNode<'a> {
     Leaf(LayerRecord<'a>),
     Node { folder: LayerRecord<'a>, children: Vec<LayerTreeNode<'a>> },
 }
 
+fn nodes_structurally_eq(a: &[LayerTreeNode], b: &[LayerTreeNode]) -> bool {
+    a.len() == b.len()
+        && a.iter().zip(b).all(|pair| match pair {
+            (LayerTreeNode::Leaf(a), LayerTreeNode::Leaf(b)) => a.structurally_eq(b),
+            (LayerTreeNode::Node { folder: a, children: a_children }, LayerTreeNode::Node { folder: b, children: b_children }) => {
+                a.structurally_eq(b) && nodes_structurally_eq(a_children, b_children)
+            }
+            _ => false,
+        })
+}
+
+/// Additional layer information is compared fully decoded and owned, so that the patterns it holds compare by their pixels.
+fn additional_info_structurally_eq(a: &[AdditionalLayerInformation], b: &[AdditionalLayerInformation]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).all(|(a, b)| a.clone().into_static(true) == b.clone().into_static(true))
+}
+
 impl<'a> LayerTreeNode<'a> {
-    fn into_static(self) -> LayerTreeNode<'static> {
+    /// Whether this is a group shown collapsed in the layers panel. The state is the one of the folder record, the
+    /// `</Layer group>` record closing the group only marks where it ends.
+    pub fn is_collapsed(&self) -> bool {
         match self {
-            LayerTreeNode::Leaf(record) => LayerTreeNode::Leaf(record.into_static()),
+            LayerTreeNode::Node { folder, .. } => folder.section_divider_type() == Some(SectionDividerType::ClosedFolder),
+            LayerTreeNode::Leaf(_) => false,
+        }
+    }
+    fn into_static(self, decode: bool) -> LayerTreeNode<'static> {
+        match self {
+            LayerTreeNode::Leaf(record) => LayerTreeNode::Leaf(record.into_static(decode)),
             LayerTreeNode::Node { folder, children } => LayerTreeNode::Node {
-                folder: folder.into_static(),
-                children: children.into_iter().map(LayerTreeNode::into_static).collect(),
+                folder: folder.into_static(decode),
+                children: children.into_iter().map(|node| node.into_static(decode)).collect(),
             },
         }
     }
 }
 
-pub(crate) fn parse_layer_and_mask_information(input: &[u8]) -> IResult<&[u8], LayerAndMaskInformation> {
-    let (input, len) = be_u32(input)?;
+pub(crate) fn parse_layer_and_mask_information<'a>(input: &'a [u8], header: &PsdHeader, options: &ParseOptions) -> IResult<&'a [u8], LayerAndMaskInformation<'a>> {
+    let (input, len) = parse_length(input, header.version().length_size())?;
     let (follow, input) = take(len)(input)?;
-    let (input, layer_info) = parse_layer_info(input)?;
+    if input.is_empty() || options.skip_layers {
+        return Ok((
+            follow,
+            LayerAndMaskInformation {
+                layer_info: Vec::new(),
+                merged_transparency: skipped_merged_transparency(input, header.version()),
+                global_layer_mask_info: Cow::Borrowed(&input[..0]),
+                additional_layer_information: Cow::Borrowed(&input[..0]),
+                global_additional_info: Vec::new(),
+                merged_alpha: None,
+            },
+        ));
+    }
+    let (input, (mut layer_info, mut merged_transparency)) = parse_layer_info(input, header, options)?;
     let (input, global_layer_mask_info) = parse_global_layer_mask_info(input)?;
-
+    let mut global_additional_info = parse_global_additional_info(input, header.version(), options);
+    // the layers of a deep document take the place of the empty layer info, and their block isn't kept among the global ones
+    if layer_info.is_empty() {
+        let deep_layers = global_additional_info.iter().position(|info| match info {
+            AdditionalLayerInformation::Unknown { key, data: Cow::Borrowed(data) } => matches!(&**key, b"Lr16" | b"Lr32" | b"Layr") && !data.is_empty(),
+            _ => false,
+        });
+        if let Some(index) = deep_layers {
+            if let AdditionalLayerInformation::Unknown { data: Cow::Borrowed(data), .. } = global_additional_info.remove(index) {
+                let (_, layers) = parse_layers(data, header, options)?;
+                (layer_info, merged_transparency) = layers;
+            }
+        }
+    }
+    let merged_alpha = global_additional_info.iter().find_map(|info| match info {
+        AdditionalLayerInformation::Unknown { key, data: Cow::Borrowed(data) } if **key == *b"Alph" => merged_alpha_channel(data, header),
+        _ => None,
+    });
     Ok((
         follow,
         LayerAndMaskInformation {
             layer_info,
+            merged_transparency,
             global_layer_mask_info: Cow::Borrowed(global_layer_mask_info),
             additional_layer_information: Cow::Borrowed(input),
+            global_additional_info,
+            merged_alpha,
         },
     ))
 }
 
-fn parse_layer_info(input: &[u8]) -> IResult<&[u8], Vec<LayerTreeNode>> {
-    let (input, len) = be_u32(input)?;
+/// Decodes PackBits compressed rows following their byte counts, `len` being the expected size of the result.
+/// Decoding stops once `len` bytes are there, and fails when the packets don't add up to exactly that many.
+pub(crate) fn unpack_bits(mut data: &[u8], len: usize) -> Result<Vec<u8>, PsdError> {
+    let mut result = Vec::with_capacity(len);
+    while let Some((&n, follow)) = data.split_first() {
+        if result.len() >= len {
+            break;
+        }
+        match n as i8 {
+            n @ 0..=127 => {
+                let n = n as usize + 1;
+                let literal = follow.get(..n).ok_or(PsdError::RleSizeMismatch { expected: len, actual: result.len() + follow.len() })?;
+                result.extend_from_slice(literal);
+                data = &follow[n..];
+            }
+            n @ -127..=-1 => {
+                let &value = follow.first().ok_or(PsdError::RleSizeMismatch { expected: len, actual: result.len() })?;
+                result.resize(result.len() + (-n) as usize + 1, value);
+                data = &follow[1..];
+            }
+            // a no-op, some writers pad with it
+            -128 => data = follow,
+        }
+    }
+    if result.len() == len {
+        Ok(result)
+    } else {
+        Err(PsdError::RleSizeMismatch { expected: len, actual: result.len() })
+    }
+}
+
+/// Compresses rows with PackBits the way RLE channels store them: the byte count of every compressed row (2 bytes each, 4 in a PSB)
+/// followed by the rows. Runs of 3 or more equal bytes are repeated, anything else is copied literally, and the `-128` no-op is never written.
+///
+/// The result is what [`ChannelInfo::compressed_bytes`] holds after its compression method, for one channel, and what the image data holds
+/// for all of its channels when given their rows one after another.
+pub fn encode_rle(rows: &[&[u8]], version: Version) -> Vec<u8> {
+    let encoded: Vec<_> = rows.iter().map(|row| pack_bits(row)).collect();
+    let mut result = Vec::with_capacity(rows.len() * version.byte_count_size() + encoded.iter().map(Vec::len).sum::<usize>());
+    for row in &encoded {
+        match version {
+            Version::Psd => result.extend_from_slice(&(row.len() as u16).to_be_bytes()),
+            Version::Psb => result.extend_from_slice(&(row.len() as u32).to_be_bytes()),
+        }
+    }
+    encoded.iter().for_each(|row| result.extend_from_slice(row));
+    result
+}
+
+fn pack_bits(row: &[u8]) -> Vec<u8> {
+    let mut result = Vec::with_capacity(row.len() + row.len().div_ceil(128));
+    let mut literal_start = 0;
+    let mut i = 0;
+    let flush_literal = |result: &mut Vec<u8>, literal: &[u8]| {
+        for chunk in literal.chunks(128) {
+            result.push(chunk.len() as u8 - 1);
+            result.extend_from_slice(chunk);
+        }
+    };
+    while i < row.len() {
+        let run = row[i..].iter().take(128).take_while(|&&byte| byte == row[i]).count();
+        if run >= 3 {
+            flush_literal(&mut result, &row[literal_start..i]);
+            result.push((1 - run as i16) as u8);
+            result.push(row[i]);
+            i += run;
+            literal_start = i;
+        } else {
+            i += run;
+        }
+    }
+    flush_literal(&mut result, &row[literal_start..]);
+    result
+}
+
+/// The `Alph` block holds a single channel the size of the document, a compression method followed by the data as in the image data section.
+fn merged_alpha_channel<'a>(data: &'a [u8], header: &PsdHeader) -> Option<ChannelInfo<'a>> {
+    let (_, compression) = map_res(be_u16::<_, Error<&[u8]>>, ImageCompression::from_u16)(data).ok()?;
+    Some(ChannelInfo {
+        channel_id: -1,
+        channel_data_length: data.len() as u64,
+        channel_data_width: header.width(),
+        channel_data_height: header.height(),
+        depth: header.depth(),
+        version: header.version(),
+        compression,
+        data: Cow::Borrowed(data),
+        raw_data: OnceCell::new(),
+    })
+}
+
+fn parse_metadata_setting<'a>(input: &'a [u8], options: &ParseOptions) -> IResult<&'a [u8], Vec<MetadataItem<'a>>> {
+    let (mut input, count) = be_u32(input)?;
+    let mut items = Vec::new();
+    for _ in 0..count {
+        let (i, _) = signature(input, &[BIM], options)?;
+        let (i, key) = take(4usize)(i)?;
+        let (i, copy_on_sheet_duplication) = be_u8(i)?;
+        // padding
+        let (i, _) = take(3usize)(i)?;
+        let (i, len) = be_u32(i)?;
+        let (i, data) = take(len)(i)?;
+        items.push(MetadataItem { key: [key[0], key[1], key[2], key[3]], copy_on_sheet_duplication: copy_on_sheet_duplication != 0, data: Cow::Borrowed(data) });
+        input = i;
+    }
+    Ok((input, items))
+}
+
+/// Whether the layer count of the layer info is negative, read without parsing the layers behind it.
+fn skipped_merged_transparency(input: &[u8], version: Version) -> bool {
+    let count = |input| -> IResult<&[u8], i16> {
+        let (input, len) = parse_length(input, version.length_size())?;
+        if len == 0 {
+            return Ok((input, 0));
+        }
+        be_i16(input)
+    };
+    matches!(count(input), Ok((_, count)) if count < 0)
+}
+
+fn parse_layer_info<'a>(input: &'a [u8], header: &PsdHeader, options: &ParseOptions) -> IResult<&'a [u8], (Vec<LayerTreeNode<'a>>, bool)> {
+    let (input, len) = parse_length(input, header.version().length_size())?;
     let (follow, input) = take(len)(input)?;
+    // documents deeper than 8 bits leave this empty and keep their layers in the `Lr16`/`Lr32` block instead
+    if input.is_empty() {
+        return Ok((follow, (Vec::new(), false)));
+    }
+    let (_, layers) = parse_layers(input, header, options)?;
+    Ok((follow, layers))
+}
+
+/// The layer count, the layer records and their channel image data, as the layer info holds them and the `Layr`, `Lr16` and `Lr32` blocks do
+/// without the length in front.
+fn parse_layers<'a>(input: &'a [u8], header: &PsdHeader, options: &ParseOptions) -> IResult<&'a [u8], (Vec<LayerTreeNode<'a>>, bool)> {
     let (mut input, layer_count) = be_i16(input)?;
     let mut layer_records = Vec::new();
     for _ in 0..layer_count.abs() {
-        let (i, layer_record) = parse_layer_record(input)?;
+        let (i, layer_record) = parse_layer_record(input, header, options)?;
         layer_records.push(layer_record);
         input = i;
     }
-    let (_input, _) = parse_channel_image_data(input, &mut layer_records)?;
+    let (i, _) = parse_channel_image_data(input, &mut layer_records)?;
     sort_channel_data(&mut layer_records);
-    let layers = into_layer_tree(layer_records);
-    Ok((follow, layers))
+    let layers = into_layer_tree(layer_records).ok_or_else(|| nom::Err::Failure(Error::new(i, ErrorKind::Verify)))?;
+    Ok((i, (layers, layer_count < 0)))
+}
+
+/// The width or height of a rectangle, 0 for an inverted one.
+fn rect_extent(start: i32, end: i32) -> u32 {
+    (end as i64 - start as i64).max(0) as u32
 }
 
 fn sort_channel_data(layer_records: &mut [LayerRecord]) {
     for layer_record in layer_records {
         let (mut channel_info, mut masks): (Vec<_>, Vec<_>) = layer_record.channel_info.drain(..).partition(|channel| channel.channel_id >= 0);
         channel_info.sort_by_key(|ch| ch.channel_id);
-        let transparency_mask = masks.iter().position(|ch| ch.channel_id == -1).map(|index| masks.swap_remove(index));
+        let transparency_mask = masks.iter().position(ChannelInfo::is_alpha).map(|index| masks.swap_remove(index));
         let mut user_supplied_layer_mask = masks.iter().position(|ch| ch.channel_id == -2).map(|index| masks.swap_remove(index));
         let mut real_user_supplied_layer_mask = masks.iter().position(|ch| ch.channel_id == -3).map(|index| masks.swap_remove(index));
         channel_info.extend(masks);
@@ -549,16 +1455,17 @@ fn sort_channel_data(layer_records: &mut [LayerRecord]) {
             ..
         }) = &layer_record.layer_mask_data
         {
+            // mask channels are stored in the size of their own rectangle, not of the layer
             if let Some(ChannelInfo { channel_data_width, channel_data_height, .. }) = &mut user_supplied_layer_mask {
-                *channel_data_width = (*layer_mask_right - *layer_mask_left) as u32;
-                *channel_data_height = (*layer_mask_bottom - *layer_mask_top) as u32;
+                *channel_data_width = rect_extent(*layer_mask_left, *layer_mask_right);
+                *channel_data_height = rect_extent(*layer_mask_top, *layer_mask_bottom);
             } else {
                 eprintln!("may be error");
             }
             if let Some(LayerMaskOptionalData { layer_mask_top, layer_mask_left, layer_mask_bottom, layer_mask_right, .. }) = optional {
                 if let Some(ChannelInfo { channel_data_width, channel_data_height, .. }) = &mut real_user_supplied_layer_mask {
-                    *channel_data_width = (*layer_mask_right - *layer_mask_left) as u32;
-                    *channel_data_height = (*layer_mask_bottom - *layer_mask_top) as u32;
+                    *channel_data_width = rect_extent(*layer_mask_left, *layer_mask_right);
+                    *channel_data_height = rect_extent(*layer_mask_top, *layer_mask_bottom);
                 } else {
                     eprintln!("may be error");
                 }
@@ -575,7 +1482,8 @@ fn sort_channel_data(layer_records: &mut [LayerRecord]) {
     }
 }
 
-fn into_layer_tree(layers: Vec<LayerRecord>) -> Vec<LayerTreeNode> {
+/// `None` when the group boundaries don't pair up with the folders closing them.
+fn into_layer_tree(layers: Vec<LayerRecord>) -> Option<Vec<LayerTreeNode>> {
     let mut stack = vec![Vec::new()];
     enum SectionDividerTypeInner {
         Start,
@@ -596,24 +1504,24 @@ fn into_layer_tree(layers: Vec<LayerRecord>) -> Vec<LayerTreeNode> {
         match divider {
             Some(SectionDividerTypeInner::Start) => stack.push(Vec::new()),
             Some(SectionDividerTypeInner::End) => {
-                let mut layers = stack.pop().expect("invalid layer structure");
+                let mut layers = stack.pop()?;
                 layers.reverse();
-                stack.last_mut().expect("invalid layer structure").push(LayerTreeNode::Node { folder: layer, children: layers });
+                stack.last_mut()?.push(LayerTreeNode::Node { folder: layer, children: layers });
             }
-            None => stack.last_mut().expect("invalid layer structure").push(LayerTreeNode::Leaf(layer)),
+            None => stack.last_mut()?.push(LayerTreeNode::Leaf(layer)),
         }
     }
-    let [mut list]: [_; 1] = stack.try_into().expect("invalid layer structure");
+    let [mut list]: [_; 1] = stack.try_into().ok()?;
     list.reverse();
-    list
+    Some(list)
 }
 
-fn parse_channel_image_data<'a, 'b>(mut input: &'a [u8], layer_records: &'b mut [LayerRecord<'a>]) -> IResult<&'a [u8], ()> {
+fn parse_channel_image_data<'a>(mut input: &'a [u8], layer_records: &mut [LayerRecord<'a>]) -> IResult<&'a [u8], ()> {
     for layer_record in layer_records {
         for channel_info in &mut layer_record.channel_info {
             let len = channel_info.channel_data_length();
             let (i, data) = take(len)(input)?;
-            let (data, compression) = map_res(be_u16, ImageCompression::from_u16)(data)?;
+            let (_, compression) = map_res(be_u16, ImageCompression::from_u16)(data)?;
             channel_info.compression = compression;
             channel_info.data = Cow::Borrowed(data);
             input = i;
@@ -622,7 +1530,8 @@ fn parse_channel_image_data<'a, 'b>(mut input: &'a [u8], layer_records: &'b mut
     Ok((input, ()))
 }
 
-fn parse_layer_record(input: &[u8]) -> IResult<&[u8], LayerRecord> {
+fn parse_layer_record<'a>(input: &'a [u8], header: &PsdHeader, options: &ParseOptions) -> IResult<&'a [u8], LayerRecord<'a>> {
+    let version = header.version();
     let (input, layer_top) = be_i32(input)?;
     let (input, layer_left) = be_i32(input)?;
     let (input, layer_bottom) = be_i32(input)?;
@@ -631,45 +1540,54 @@ fn parse_layer_record(input: &[u8]) -> IResult<&[u8], LayerRecord> {
     let mut channel_info = Vec::new();
     for _ in 0..channels {
         let (i, channel_id) = be_i16(input)?;
-        let (i, channel_data_length) = be_u32(i)?;
+        let (i, channel_data_length) = parse_length(i, version.length_size())?;
         channel_info.push(ChannelInfo {
             channel_id,
             channel_data_length,
-            channel_data_width: (layer_right - layer_left) as u32,
-            channel_data_height: (layer_bottom - layer_top) as u32,
+            channel_data_width: rect_extent(layer_left, layer_right),
+            channel_data_height: rect_extent(layer_top, layer_bottom),
+            depth: header.depth(),
+            version,
             compression: ImageCompression::Raw,
             data: Cow::Borrowed(&i[..0]),
             raw_data: OnceCell::new(),
         });
         input = i;
     }
-    let (input, _) = tag(b"8BIM")(input)?;
+    let (input, _) = signature(input, &[BIM], options)?;
     let (input, blend_mode) = map_res(take(4usize), BlendMode::try_from)(input)?;
     let (input, opacity) = be_u8(input)?;
     let (input, clipping) = map_res(be_u8, Clipping::try_from)(input)?;
     let (input, flags) = map_res(be_u8, |flags| LayerRecordFlags::from_bits(flags).ok_or(flags))(input)?;
     let (input, _) = take(1usize)(input)?;
     let (input, len) = be_u32(input)?;
-    let (follow, input) = take(len)(input)?;
-    let (input, layer_mask_data_len) = be_u32(input)?;
-    let (input, layer_mask_data) = take(layer_mask_data_len)(input)?;
+    let (follow, extra_data) = take(len)(input)?;
+    // the fields have to account for exactly `len` bytes, a miscount is reported instead of misreading the records that follow
+    let length_mismatch = || nom::Err::Failure(Error::new(extra_data, ErrorKind::LengthValue));
+    let mismatch = |_: nom::Err<Error<&[u8]>>| length_mismatch();
+    let (input, layer_mask_data_len) = be_u32(extra_data).map_err(mismatch)?;
+    let (input, layer_mask_data) = take(layer_mask_data_len)(input).map_err(mismatch)?;
     let (_, layer_mask_data) = parse_layer_mask_data(layer_mask_data)?;
-    let (input, layer_blending_ranges_len) = be_u32(input)?;
-    let (input, layer_blending_ranges_data) = take(layer_blending_ranges_len)(input)?;
-    let (input, layer_name_len) = be_u8(input)?;
-    let (input, layer_name) = take(layer_name_len)(input)?;
-    let mut input = &input[3 - (layer_name_len as usize & 3)..];
+    let (input, layer_blending_ranges_len) = be_u32(input).map_err(mismatch)?;
+    let (input, layer_blending_ranges_data) = take(layer_blending_ranges_len)(input).map_err(mismatch)?;
+    let (input, layer_name_len) = be_u8(input).map_err(mismatch)?;
+    let (input, layer_name) = take(layer_name_len)(input).map_err(mismatch)?;
+    let (mut input, _) = take(3 - (layer_name_len as usize & 3))(input).map_err(mismatch)?;
+    let additional_info_offset = extra_data.len() - input.len();
     let mut additional_layer_info = Vec::new();
-    while !input.is_empty() {
-        let (i, _) = alt((tag(b"8BIM"), tag(b"8B64")))(input)?;
+    // some writers pad the extra data to an even or 4-byte length, which leaves up to 3 bytes after the last block
+    while input.len() > 3 {
+        let (i, _) = signature(input, &[BIM, B64], options)?;
         let (i, key) = take(4usize)(i)?;
-        let (i, len) = be_u32(i)?;
-        let (i, data) = take(len as usize)(i)?;
-        let (follow, info) = parse_additional_layer_info(key.try_into().unwrap(), data)?;
-        assert_eq!(follow.len(), 0);
+        let (i, len) = parse_length(i, version.block_length_size(key)).map_err(mismatch)?;
+        let (i, data) = take(len)(i).map_err(mismatch)?;
+        let (_, info) = parse_additional_layer_info(key.try_into().unwrap(), data, options)?;
         additional_layer_info.push(info);
         input = i;
     }
+    if input.iter().any(|&byte| byte != 0) {
+        return Err(length_mismatch());
+    }
     Ok((
         follow,
         LayerRecord {
@@ -689,6 +1607,8 @@ fn parse_layer_record(input: &[u8]) -> IResult<&[u8], LayerRecord> {
             layer_blending_ranges_data: Cow::Borrowed(layer_blending_ranges_data),
             layer_name: Cow::Borrowed(layer_name),
             additional_layer_info,
+            extra_data: Cow::Borrowed(extra_data),
+            additional_info_offset,
         },
     ))
 }
@@ -703,7 +1623,13 @@ fn parse_layer_mask_data(input: &[u8]) -> IResult<&[u8], Option<LayerMaskData>>
     let (input, layer_mask_right) = be_i32(input)?;
     let (input, default_color) = be_u8(input)?;
     let (input, flags) = map_res(be_u8, |flags| LayerMaskFlags::from_bits(flags).ok_or(flags))(input)?;
-    if input.len() == 2 {
+    let (input, [user_mask_adjustments, vector_mask_adjustments]) = if flags.contains(LayerMaskFlags::INDICATES_THAT_THE_USER_AND_OR_VECTOR_MASKS_HAVE_PARAMEWTERS_APPLIED_TO_THEM) {
+        parse_mask_parameters(input)?
+    } else {
+        (input, [None, None])
+    };
+    // only 2 bytes of padding follow when there is no real user mask
+    if input.len() < 18 {
         return Ok((
             &input[..0],
             Some(LayerMaskData {
@@ -713,6 +1639,8 @@ fn parse_layer_mask_data(input: &[u8]) -> IResult<&[u8], Option<LayerMaskData>>
                 layer_mask_right,
                 default_color,
                 flags,
+                user_mask_adjustments,
+                vector_mask_adjustments,
                 optional: None,
             }),
         ));
@@ -732,6 +1660,8 @@ fn parse_layer_mask_data(input: &[u8]) -> IResult<&[u8], Option<LayerMaskData>>
             layer_mask_right,
             default_color,
             flags,
+            user_mask_adjustments,
+            vector_mask_adjustments,
             optional: Some(LayerMaskOptionalData {
                 real_flags,
                 real_user_mask_background,
@@ -744,40 +1674,176 @@ fn parse_layer_mask_data(input: &[u8]) -> IResult<&[u8], Option<LayerMaskData>>
     ))
 }
 
+/// Parses the mask parameters, user mask density/feather then vector mask density/feather, each present only when its bit in the leading flags is set.
+fn parse_mask_parameters(input: &[u8]) -> IResult<&[u8], [Option<MaskAdjustments>; 2]> {
+    let (mut input, parameter_flags) = be_u8(input)?;
+    let mut adjustments = [None, None];
+    for (index, adjustment) in adjustments.iter_mut().enumerate() {
+        let mut density = None;
+        let mut feather = None;
+        if parameter_flags & (1 << (index * 2)) != 0 {
+            let (i, value) = be_u8(input)?;
+            density = Some(value as f64 / 255.0);
+            input = i;
+        }
+        if parameter_flags & (1 << (index * 2 + 1)) != 0 {
+            let (i, value) = be_f64(input)?;
+            feather = Some(value);
+            input = i;
+        }
+        if density.is_some() || feather.is_some() {
+            *adjustment = Some(MaskAdjustments { density: density.unwrap_or(1.0), feather: feather.unwrap_or(0.0) });
+        }
+    }
+    Ok((input, adjustments))
+}
+
+/// Unlike the blocks of a layer record, a malformed global block only ends the list, the raw bytes stay available either way.
+/// Without `strict_signatures` the blocks after it are still read, resuming at the next signature.
+fn parse_global_additional_info<'a>(mut input: &'a [u8], version: Version, options: &ParseOptions) -> Vec<AdditionalLayerInformation<'a>> {
+    let mut blocks = Vec::new();
+    // anything shorter than a block header is padding
+    while input.len() >= 12 {
+        let block = |input: &'a [u8]| -> IResult<&'a [u8], AdditionalLayerInformation<'a>> {
+            let (input, _) = signature(input, &[BIM, B64], options)?;
+            let (input, key) = take(4usize)(input)?;
+            let (input, len) = parse_length(input, version.block_length_size(key))?;
+            let (input, data) = take(len)(input)?;
+            let (_, info) = parse_additional_layer_info(key.try_into().unwrap(), data, options)?;
+            Ok((input, info))
+        };
+        match block(input) {
+            Ok((i, info)) => {
+                blocks.push(info);
+                input = i;
+            }
+            Err(_) => {
+                let next = [BIM, B64].iter().filter_map(|sig| find_next_signature(&input[1..], &sig[..])).min();
+                match next {
+                    Some(offset) if !options.strict_signatures => input = &input[offset + 1..],
+                    _ => break,
+                }
+            }
+        }
+    }
+    blocks
+}
+
+/// The color space, the four components of the color and the opacity of an `FMsk` block.
+fn parse_filter_mask(input: &[u8]) -> IResult<&[u8], (u16, [u16; 4], u16)> {
+    let (input, color_space) = be_u16(input)?;
+    let (input, c0) = be_u16(input)?;
+    let (input, c1) = be_u16(input)?;
+    let (input, c2) = be_u16(input)?;
+    let (input, c3) = be_u16(input)?;
+    let (input, opacity) = be_u16(input)?;
+    Ok((input, (color_space, [c0, c1, c2, c3], opacity)))
+}
+
 fn parse_global_layer_mask_info(input: &[u8]) -> IResult<&[u8], &[u8]> {
     let (input, len) = be_u32(input)?;
     take(len)(input)
 }
 
-fn parse_additional_layer_info<'a>(key: &'a [u8; 4], data: &'a [u8]) -> IResult<&'a [u8], AdditionalLayerInformation<'a>> {
+fn parse_additional_layer_info<'a>(key: &'a [u8; 4], data: &'a [u8], options: &ParseOptions) -> IResult<&'a [u8], AdditionalLayerInformation<'a>> {
     match key {
-        b"lsct" => {
+        b"lsct" | b"lsdk" => {
             let (data, section_type) = map_res(be_u32, SectionDividerType::from_u32)(data)?;
-            if data.is_empty() {
-                return Ok((data, AdditionalLayerInformation::SectionDivider { section_divider_type: section_type, key: None, sub_type: None }));
-            }
-            let (data, _) = tag(b"8BIM")(data)?;
-            let (data, blend_mode) = map_res(take(4usize), BlendMode::try_from)(data)?;
-            if data.is_empty() {
-                return Ok((
-                    data,
-                    AdditionalLayerInformation::SectionDivider {
-                        section_divider_type: section_type,
-                        key: Some(blend_mode),
-                        sub_type: None,
-                    },
-                ));
-            }
-            let (data, sub_type) = map_res(be_u32, SectionDividerSubType::from_u32)(data)?;
+            let (key, sub_type, extra) = match data {
+                [] => (None, None, data),
+                _ => {
+                    let (data, _) = signature(data, &[BIM], options)?;
+                    let (data, blend_mode) = map_res(take(4usize), BlendMode::try_from)(data)?;
+                    match be_u32::<_, Error<&[u8]>>(data) {
+                        Ok((extra, sub_type)) => {
+                            // a sub type this crate doesn't know about is left out
+                            (Some(blend_mode), SectionDividerSubType::from_u32(sub_type).ok(), extra)
+                        }
+                        Err(_) => (Some(blend_mode), None, data),
+                    }
+                }
+            };
             Ok((
-                data,
-                AdditionalLayerInformation::SectionDivider {
-                    section_divider_type: section_type,
-                    key: Some(blend_mode),
-                    sub_type: Some(sub_type),
-                },
+                &data[..0],
+                AdditionalLayerInformation::SectionDivider { section_divider_type: section_type, key, sub_type, extra: Cow::Borrowed(extra) },
             ))
         }
+        b"luni" => match parse_unicode_string(data) {
+            Ok((_, name)) => Ok((&data[..0], AdditionalLayerInformation::UnicodeName(name))),
+            Err(_) => Ok((&data[..0], AdditionalLayerInformation::Unknown { key: Cow::Borrowed(key), data: Cow::Borrowed(data) })),
+        },
+        b"lyid" => match be_u32::<_, Error<&[u8]>>(data) {
+            Ok((_, id)) => Ok((&data[..0], AdditionalLayerInformation::LayerId(id))),
+            Err(_) => Ok((&data[..0], AdditionalLayerInformation::Unknown { key: Cow::Borrowed(key), data: Cow::Borrowed(data) })),
+        },
+        // the color followed by 6 bytes of padding
+        b"lclr" => match map_res(be_u16::<_, Error<&[u8]>>, LayerColor::from_u16)(data) {
+            Ok((_, color)) => Ok((&data[..0], AdditionalLayerInformation::ColorLabel(color))),
+            Err(_) => Ok((&data[..0], AdditionalLayerInformation::Unknown { key: Cow::Borrowed(key), data: Cow::Borrowed(data) })),
+        },
+        // one byte followed by 3 bytes of padding
+        b"tsly" => match be_u8::<_, Error<&[u8]>>(data) {
+            Ok((_, shapes)) => Ok((&data[..0], AdditionalLayerInformation::TransparencyShapesLayer(shapes != 0))),
+            Err(_) => Ok((&data[..0], AdditionalLayerInformation::Unknown { key: Cow::Borrowed(key), data: Cow::Borrowed(data) })),
+        },
+        b"lspf" => match be_u32::<_, Error<&[u8]>>(data) {
+            Ok((_, flags)) => Ok((&data[..0], AdditionalLayerInformation::ProtectionFlags(ProtectionFlags::from_bits_truncate(flags)))),
+            Err(_) => Ok((&data[..0], AdditionalLayerInformation::Unknown { key: Cow::Borrowed(key), data: Cow::Borrowed(data) })),
+        },
+        b"shmd" => match parse_metadata_setting(data, options) {
+            Ok((_, items)) => Ok((&data[..0], AdditionalLayerInformation::Metadata(items))),
+            Err(_) => Ok((&data[..0], AdditionalLayerInformation::Unknown { key: Cow::Borrowed(key), data: Cow::Borrowed(data) })),
+        },
+        b"fxrp" => match be_f64::<_, Error<&[u8]>>(data).and_then(|(data, x)| be_f64(data).map(|(data, y)| (data, (x, y)))) {
+            Ok((_, (x, y))) => Ok((&data[..0], AdditionalLayerInformation::ReferencePoint { x, y })),
+            Err(_) => Ok((&data[..0], AdditionalLayerInformation::Unknown { key: Cow::Borrowed(key), data: Cow::Borrowed(data) })),
+        },
+        b"GdFl" => match parse_gradient_fill(data) {
+            Ok((_, Some(gradient))) => Ok((&data[..0], AdditionalLayerInformation::GradientFill(gradient))),
+            _ => Ok((&data[..0], AdditionalLayerInformation::Unknown { key: Cow::Borrowed(key), data: Cow::Borrowed(data) })),
+        },
+        b"vmsk" | b"vsms" => match parse_vector_mask(data) {
+            Ok((_, mask)) if key == b"vmsk" => Ok((&data[..0], AdditionalLayerInformation::VectorMask(mask))),
+            Ok((_, mask)) => Ok((&data[..0], AdditionalLayerInformation::VectorStrokeMask(mask))),
+            Err(_) => Ok((&data[..0], AdditionalLayerInformation::Unknown { key: Cow::Borrowed(key), data: Cow::Borrowed(data) })),
+        },
+        b"vogk" => match be_u32::<_, Error<&[u8]>>(data).and_then(|(data, _version)| parse_versioned_descriptor(data)) {
+            Ok((_, descriptor)) => Ok((&data[..0], AdditionalLayerInformation::VectorOrigination(descriptor))),
+            Err(_) => Ok((&data[..0], AdditionalLayerInformation::Unknown { key: Cow::Borrowed(key), data: Cow::Borrowed(data) })),
+        },
+        b"lfx2" | b"lmfx" => match parse_layer_effects(data) {
+            Ok((_, effects)) if key == b"lfx2" => Ok((&data[..0], AdditionalLayerInformation::Effects(effects))),
+            Ok((_, effects)) => Ok((&data[..0], AdditionalLayerInformation::MultipleEffects(effects))),
+            Err(_) => Ok((&data[..0], AdditionalLayerInformation::Unknown { key: Cow::Borrowed(key), data: Cow::Borrowed(data) })),
+        },
+        b"FMsk" => match parse_filter_mask(data) {
+            Ok((_, (color_space, color, opacity))) => Ok((&data[..0], AdditionalLayerInformation::FilterMask { color_space, color, opacity })),
+            Err(_) => Ok((&data[..0], AdditionalLayerInformation::Unknown { key: Cow::Borrowed(key), data: Cow::Borrowed(data) })),
+        },
+        b"TySh" => match parse_type_tool(data) {
+            Ok((_, text)) => Ok((&data[..0], AdditionalLayerInformation::TypeTool(text))),
+            Err(_) => Ok((&data[..0], AdditionalLayerInformation::Unknown { key: Cow::Borrowed(key), data: Cow::Borrowed(data) })),
+        },
+        b"Txt2" => match parse_engine_data(data) {
+            Ok((_, engine_data)) => Ok((&data[..0], AdditionalLayerInformation::TextEngineData(engine_data))),
+            Err(_) => Ok((&data[..0], AdditionalLayerInformation::Unknown { key: Cow::Borrowed(key), data: Cow::Borrowed(data) })),
+        },
+        b"Patt" | b"Pat2" | b"Pat3" => match parse_patterns(data) {
+            Ok((_, patterns)) => Ok((&data[..0], AdditionalLayerInformation::Patterns(patterns))),
+            Err(_) => Ok((&data[..0], AdditionalLayerInformation::Unknown { key: Cow::Borrowed(key), data: Cow::Borrowed(data) })),
+        },
+        b"SoLd" | b"SoLE" => match take::<_, _, Error<&[u8]>>(4usize)(data).and_then(|(data, _key)| be_u32(data)).and_then(|(data, _version)| parse_versioned_descriptor(data)) {
+            Ok((_, descriptor)) => Ok((&data[..0], AdditionalLayerInformation::PlacedLayer(descriptor))),
+            Err(_) => Ok((&data[..0], AdditionalLayerInformation::Unknown { key: Cow::Borrowed(key), data: Cow::Borrowed(data) })),
+        },
+        b"lnkD" | b"lnk2" | b"lnk3" => match parse_linked_files(data) {
+            Ok((_, files)) => Ok((&data[..0], AdditionalLayerInformation::LinkedFiles(files))),
+            Err(_) => Ok((&data[..0], AdditionalLayerInformation::Unknown { key: Cow::Borrowed(key), data: Cow::Borrowed(data) })),
+        },
+        b"clrL" => match parse_color_lookup(data) {
+            Ok((_, adjustment)) => Ok((&data[..0], AdditionalLayerInformation::Adjustment(adjustment))),
+            Err(_) => Ok((&data[..0], AdditionalLayerInformation::Unknown { key: Cow::Borrowed(key), data: Cow::Borrowed(data) })),
+        },
         _ => Ok((&data[..0], AdditionalLayerInformation::Unknown { key: Cow::Borrowed(key), data: Cow::Borrowed(data) })),
     }
 }
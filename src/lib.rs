@@ -1,16 +1,50 @@
+use std::borrow::Cow;
+use std::collections::BTreeSet;
+use std::convert::TryFrom;
+use std::fmt;
+use std::io::Read;
+
+use nom::error::ErrorKind;
+
 use crate::color_mode::{parse_color_mode, ColorModeData};
-use crate::header::{parse_header, PsdHeader};
+use crate::descriptor::FloatUnit;
+use crate::error::PsdError;
+use crate::header::{parse_header, ColorMode, PsdHeader};
 use crate::image_data::{parse_image_data, ImageData};
 use crate::image_resource::{parse_image_resources, ImageResources};
-use crate::layer_info::{parse_layer_and_mask_information, LayerAndMaskInformation};
+use crate::layer_info::{parse_layer_and_mask_information, AdditionalLayerInformation, LayerAndMaskInformation, LayerRecord, LayerTreeNode, SectionDividerType};
 
+pub mod adjustment;
+pub mod blend;
+mod color;
+pub mod composite;
 pub mod color_mode;
+mod convert;
+pub mod descriptor;
+pub mod effects;
+pub mod engine_data;
+pub mod error;
+pub mod fill;
+pub mod fixed;
 pub mod header;
 pub mod image_data;
 pub mod image_resource;
+mod inflate;
 pub mod layer_info;
+pub mod linked;
+pub mod path;
+pub mod pattern;
+pub mod rect;
+#[cfg(feature = "serde")]
+mod serialize;
+mod signature;
+pub mod slices;
+mod strings;
+pub mod text;
+mod write;
 
-#[derive(Debug, Eq, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Psd<'a> {
     header: PsdHeader,
     color_mode: ColorModeData<'a>,
@@ -20,9 +54,17 @@ pub struct Psd<'a> {
 }
 
 impl<'a> Psd<'a> {
+    /// The same as [`parse_psd`].
+    pub fn parse(input: &'a [u8]) -> Result<Psd<'a>, PsdError> {
+        parse_psd(input)
+    }
     pub fn header(&self) -> &PsdHeader {
         &self.header
     }
+    /// The bytes of one sample of the image data, 1 for 1-bit documents whose samples are packed 8 to a byte.
+    pub fn depth_bytes(&self) -> usize {
+        (self.header.depth() as usize).div_ceil(8)
+    }
     pub fn color_mode(&self) -> &ColorModeData<'a> {
         &self.color_mode
     }
@@ -35,23 +77,347 @@ impl<'a> Psd<'a> {
     pub fn image_data(&self) -> &ImageData<'a> {
         &self.image_data
     }
+    pub fn summary(&self) -> PsdSummary {
+        fn count_layers(nodes: &[LayerTreeNode], summary: &mut PsdSummary) {
+            for node in nodes {
+                match node {
+                    LayerTreeNode::Leaf(_) => summary.layer_count += 1,
+                    LayerTreeNode::Node { children, .. } => {
+                        summary.group_count += 1;
+                        count_layers(children, summary);
+                    }
+                }
+            }
+        }
+        let header = self.header();
+        let mut summary = PsdSummary {
+            dimensions: (header.width(), header.height()),
+            color_mode: header.color_mode(),
+            depth: header.depth(),
+            channels: header.channels(),
+            layer_count: 0,
+            group_count: 0,
+            has_thumbnail: self.image_resources.find(1036).or_else(|| self.image_resources.find(1033)).is_some(),
+            has_icc: self.image_resources.find(1039).is_some(),
+        };
+        count_layers(self.layer_information.layer_info(), &mut summary);
+        summary
+    }
+    /// All layers in the order the layers panel lists them, top-most first, each group's folder record followed by its contents.
+    /// Folder records are only included when `include_groups` is set.
+    pub fn layers_flat(&self, include_groups: bool) -> Vec<&LayerRecord<'a>> {
+        fn flatten<'t, 'a>(nodes: &'t [LayerTreeNode<'a>], include_groups: bool, layers: &mut Vec<&'t LayerRecord<'a>>) {
+            for node in nodes {
+                match node {
+                    LayerTreeNode::Leaf(record) => layers.push(record),
+                    LayerTreeNode::Node { folder, children } => {
+                        if include_groups {
+                            layers.push(folder);
+                        }
+                        flatten(children, include_groups, layers);
+                    }
+                }
+            }
+        }
+        let mut layers = Vec::new();
+        flatten(self.layer_information.layer_info(), include_groups, &mut layers);
+        layers
+    }
+    /// A JSON array describing the layers panel, to write as a sidecar next to layers exported as images so that their organization can be rebuilt.
+    ///
+    /// It holds an object for every layer and group in the order of [`LayerAndMaskInformation::iter_layers`], with its `name`,
+    /// its nesting `depth`, whether it is a `group`, its `color_label` ([`LayerColor::name`](layer_info::LayerColor::name), `null` without an `lclr` block)
+    /// and whether it is `visible` ([`LayerRecord::is_visible`]).
+    pub fn layer_panel_json(&self) -> String {
+        let mut json = String::from("[");
+        for (index, (record, depth)) in self.layer_information.iter_layers().enumerate() {
+            if index > 0 {
+                json.push(',');
+            }
+            let color_label = record.color_label().map_or_else(|| "null".to_string(), |color| format!("\"{}\"", color.name()));
+            let group = matches!(record.section_divider_type(), Some(SectionDividerType::OpenFolder | SectionDividerType::ClosedFolder));
+            json += &format!(
+                "{{\"name\":{},\"depth\":{},\"group\":{},\"color_label\":{},\"visible\":{}}}",
+                json_string(&record.unicode_name()),
+                depth,
+                group,
+                color_label,
+                record.is_visible()
+            );
+        }
+        json.push(']');
+        json
+    }
+    /// PostScript names of the fonts used by the text layers, across all of their style runs.
+    pub fn fonts_used(&self) -> BTreeSet<String> {
+        self.layers_flat(false).into_iter().filter_map(|layer| layer.text()).flat_map(|text| text.fonts()).collect()
+    }
+    /// The content of every text layer with the id (`lyid`) of its layer, in panel order. Text layers with an empty text are included
+    /// with an empty string, the few without an id (which only very old writers leave out) are skipped.
+    pub fn iter_text_layers(&self) -> impl Iterator<Item = (LayerId, String)> {
+        let texts: Vec<_> = self.layers_flat(false).into_iter().filter_map(|record| Some((record.layer_id()?, record.text()?.text().to_string()))).collect();
+        texts.into_iter()
+    }
+    /// Every additional layer information block of the document, paired with the id (`lyid`) of the layer it belongs to.
+    /// The global blocks come first with `None`, followed by the blocks of each layer in panel order.
+    pub fn all_additional_info(&self) -> impl Iterator<Item = (Option<LayerId>, &AdditionalLayerInformation<'a>)> + '_ {
+        let global = self.layer_information.global_additional_info().iter().map(|info| (None, info));
+        let layers = self.layers_flat(true).into_iter().flat_map(|record| {
+            let id = record.layer_id();
+            record.additional_layer_info().iter().map(move |info| (id, info))
+        });
+        global.chain(layers)
+    }
+    /// Converts the length `px` in pixels to `unit`, using the resolution of the document and 72 pixels per inch when it has no resolution info.
+    /// `Distance` is taken as points like Photoshop does, and units that aren't lengths, such as `Percent` or `Angle`, leave `px` as it is.
+    pub fn to_document_units(&self, px: f64, unit: FloatUnit) -> f64 {
+        units_per_inch(unit).map_or(px, |units| px / self.pixels_per_inch() * units)
+    }
+    /// The inverse of [`to_document_units`](Self::to_document_units), converting the length `value` in `unit` to pixels.
+    pub fn from_document_units(&self, value: f64, unit: FloatUnit) -> f64 {
+        units_per_inch(unit).map_or(value, |units| value / units * self.pixels_per_inch())
+    }
+    fn pixels_per_inch(&self) -> f64 {
+        self.image_resources.resolution_info().map_or(72.0, |info| info.pixels_per_inch()).max(f64::MIN_POSITIVE)
+    }
+    /// Checks that the composite image data decompresses to exactly the `width * height * channels` samples the header describes,
+    /// so that a truncated or corrupt file fails here instead of panicking when the image is decoded.
+    pub fn validate_dimensions_against_data(&self) -> Result<(), PsdError> {
+        let header = &self.header;
+        let expected = plane_len(header.width(), header.height(), header.depth(), header.channels() as usize)?;
+        let actual = self.image_data.decoded_len()?;
+        if actual == expected {
+            Ok(())
+        } else {
+            Err(PsdError::ImageDataSizeMismatch { expected, actual })
+        }
+    }
+    /// Compares two documents by their content: channels by their decoded data whatever their compression, regardless of which of them
+    /// have been decoded already and whether the data is borrowed or owned. Unlike `==` this holds between a document, its [`into_static`](Self::into_static)
+    /// and the document read back from what [`write_to`](Self::write_to) writes.
+    pub fn structurally_eq(&self, other: &Psd) -> bool {
+        self.header == other.header
+            && self.color_mode == other.color_mode
+            && self.image_resources == other.image_resources
+            && self.layer_information.structurally_eq(&other.layer_information)
+            && self.image_data.structurally_eq(&other.image_data)
+    }
+    /// Roughly how many bytes the document holds on its own: data it owns rather than borrows from the input, such as after
+    /// [`into_static`](Self::into_static), and the channels decoded so far. Only byte buffers are counted, not the values parsed from them
+    /// such as texts, descriptors and paths, nor the vectors holding the layers.
+    pub fn memory_footprint(&self) -> usize {
+        self.color_mode.memory_footprint() + self.image_resources.memory_footprint() + self.layer_information.memory_footprint() + self.image_data.memory_footprint()
+    }
+    /// Takes ownership of all the data, decoding every channel first. Channels that can't be decoded, such as zip compressed ones
+    /// without the `zip` feature, are kept compressed and fail the same way when used.
     pub fn into_static(self) -> Psd<'static> {
+        self.into_static_with(true)
+    }
+    /// An owned copy of the document. Unlike [`into_static`](Self::into_static), channels are copied as stored and only decoded when first used,
+    /// the ones already decoded keep their decoded data.
+    pub fn clone_owned(&self) -> Psd<'static> {
+        self.clone().into_static_with(false)
+    }
+    fn into_static_with(self, decode: bool) -> Psd<'static> {
         let Psd { header, color_mode, image_resources, layer_information, image_data } = self;
         Psd {
             header,
             color_mode: color_mode.into_static(),
             image_resources: image_resources.into_static(),
-            layer_information: layer_information.into_static(),
-            image_data: image_data.into_static(),
+            layer_information: layer_information.into_static(decode),
+            image_data: image_data.into_static(decode),
+        }
+    }
+}
+
+/// How many `unit`s make an inch, `None` for units that aren't physical lengths.
+fn units_per_inch(unit: FloatUnit) -> Option<f64> {
+    match unit {
+        FloatUnit::Points | FloatUnit::Distance => Some(72.0),
+        FloatUnit::Millimeters => Some(25.4),
+        _ => None,
+    }
+}
+
+/// The length of `data` when it is owned, 0 when it borrows from the input.
+// whether it is owned is the whole point, so it can't take a slice
+#[allow(clippy::ptr_arg)]
+pub(crate) fn owned_len(data: &Cow<[u8]>) -> usize {
+    match data {
+        Cow::Borrowed(_) => 0,
+        Cow::Owned(data) => data.len(),
+    }
+}
+
+/// The size in bytes of `planes` planes of `width` x `height` samples of `depth` bits, rows padded to whole bytes.
+/// Computed in `u64`, so it doesn't wrap where `usize` is 32 bits but fails with [`PsdError::TooLarge`] when the result doesn't fit in one.
+pub(crate) fn plane_len(width: u32, height: u32, depth: u16, planes: usize) -> Result<usize, PsdError> {
+    let row_len = (width as u64 * depth as u64).div_ceil(8);
+    row_len.checked_mul(height as u64).and_then(|len| len.checked_mul(planes as u64)).and_then(|len| usize::try_from(len).ok()).ok_or(PsdError::TooLarge)
+}
+
+/// The id of a layer, unique within the document and kept when layers are moved, as stored in its `lyid` block.
+pub type LayerId = u32;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PsdSummary {
+    dimensions: (u32, u32),
+    color_mode: ColorMode,
+    depth: u16,
+    channels: u16,
+    layer_count: usize,
+    group_count: usize,
+    has_thumbnail: bool,
+    has_icc: bool,
+}
+
+impl PsdSummary {
+    pub fn dimensions(&self) -> (u32, u32) {
+        self.dimensions
+    }
+    pub fn color_mode(&self) -> ColorMode {
+        self.color_mode
+    }
+    pub fn depth(&self) -> u16 {
+        self.depth
+    }
+    pub fn channels(&self) -> u16 {
+        self.channels
+    }
+    pub fn layer_count(&self) -> usize {
+        self.layer_count
+    }
+    pub fn group_count(&self) -> usize {
+        self.group_count
+    }
+    pub fn has_thumbnail(&self) -> bool {
+        self.has_thumbnail
+    }
+    pub fn has_icc(&self) -> bool {
+        self.has_icc
+    }
+}
+
+impl fmt::Display for PsdSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (width, height) = self.dimensions;
+        let yes_no = |value: bool| if value { "yes" } else { "no" };
+        write!(
+            f,
+            "{}x{} {:?} {}-bit, {} channels, {} layers in {} groups, thumbnail: {}, ICC profile: {}",
+            width,
+            height,
+            self.color_mode,
+            self.depth,
+            self.channels,
+            self.layer_count,
+            self.group_count,
+            yes_no(self.has_thumbnail),
+            yes_no(self.has_icc)
+        )
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ParseOptions {
+    /// Reject blocks whose signature (`8BPS`, `8BIM`, `8B64`) doesn't match.
+    /// When disabled, parsing continues past a mismatch as if the signature were correct.
+    pub strict_signatures: bool,
+    /// Skip the layer and mask information by its length instead of parsing it, for when only the composite is needed.
+    /// [`Psd::layer_information`] is then empty but for [`merged_transparency`](layer_info::LayerAndMaskInformation::merged_transparency),
+    /// which comes from the sign of the layer count, and writing the document back drops its layers.
+    pub skip_layers: bool,
+}
+
+impl Default for ParseOptions {
+    fn default() -> Self {
+        ParseOptions { strict_signatures: true, skip_layers: false }
+    }
+}
+
+pub fn parse_psd(input: &[u8]) -> Result<Psd<'_>, PsdError> {
+    parse_psd_with_options(input, &ParseOptions::default())
+}
+
+pub fn parse_psd_with_options<'a>(input: &'a [u8], options: &ParseOptions) -> Result<Psd<'a>, PsdError> {
+    parse_psd_inner(input, options).map(|(_, psd)| psd)
+}
+
+/// Reads `reader` to the end and parses what it read, for instance an opened file.
+///
+/// The returned document owns its data and doesn't borrow from the buffer read into, which is dropped. Channels are kept as stored
+/// and decoded when first used, like [`Psd::clone_owned`] does, so the document holds about as much memory as the file is large.
+pub fn parse_psd_reader<R: Read>(mut reader: R) -> Result<Psd<'static>, PsdError> {
+    let mut input = Vec::new();
+    reader.read_to_end(&mut input)?;
+    Ok(parse_psd(&input)?.into_static_with(false))
+}
+
+/// Parses a PSD at the beginning of `input`, also returning how many bytes it occupies.
+/// This allows reading a document embedded in a larger buffer and continuing after it.
+pub fn parse_psd_with_len(input: &[u8]) -> Result<(Psd<'_>, usize), PsdError> {
+    let (follow, psd) = parse_psd_inner(input, &ParseOptions::default())?;
+    Ok((psd, input.len() - follow.len()))
+}
+
+/// `text` as a quoted JSON string.
+fn json_string(text: &str) -> String {
+    let mut json = String::with_capacity(text.len() + 2);
+    json.push('"');
+    for c in text.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            '\r' => json.push_str("\\r"),
+            '\t' => json.push_str("\\t"),
+            c if (c as u32) < 0x20 => json += &format!("\\u{:04x}", c as u32),
+            c => json.push(c),
+        }
+    }
+    json.push('"');
+    json
+}
+
+/// Turns the error of the parser of `section` into a [`PsdError`] from its kind and where in `whole` it stopped.
+///
+/// The kinds are specific enough for this: `Tag` comes from signatures (and the reserved bytes of the header), `Verify` in the header
+/// from the check of the field at that offset, `MapRes` from the color mode of the header or the compression method of the image data,
+/// `TooLarge` from raw image data too large for the target, and `LengthValue` from a layer record whose extra data doesn't match its length,
+/// with the extra data as the input.
+fn section_error<'a>(whole: &'a [u8], section: &'static str) -> impl FnOnce(nom::Err<nom::error::Error<&'a [u8]>>) -> PsdError {
+    move |error| {
+        let error = match error {
+            nom::Err::Error(error) | nom::Err::Failure(error) => error,
+            nom::Err::Incomplete(_) => return PsdError::Truncated { section },
+        };
+        let offset = error.input.as_ptr() as usize - whole.as_ptr() as usize;
+        let u16_at = |offset: usize| whole.get(offset..offset + 2).map_or(0, |bytes| u16::from_be_bytes([bytes[0], bytes[1]]));
+        let u32_at = |offset: usize| whole.get(offset..offset + 4).map_or(0, |bytes| u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]));
+        match (section, error.code, offset) {
+            (_, ErrorKind::Eof, _) => PsdError::Truncated { section },
+            ("header", ErrorKind::Tag, 0) => PsdError::BadSignature { offset },
+            ("header", ErrorKind::Verify, 4) => PsdError::UnsupportedVersion(u16_at(4)),
+            ("header", ErrorKind::Verify, 12) => PsdError::InvalidChannelCount(u16_at(12)),
+            ("header", ErrorKind::Verify, 14 | 18) => PsdError::InvalidDimensions { width: u32_at(18), height: u32_at(14) },
+            ("header", ErrorKind::Verify, 22) => PsdError::UnsupportedDepth(u16_at(22)),
+            ("header", ErrorKind::MapRes, _) => PsdError::UnknownColorMode { value: u16_at(offset), offset },
+            ("header", _, _) => PsdError::Malformed { section, offset },
+            (_, ErrorKind::Tag, _) => PsdError::BadSignature { offset },
+            (_, ErrorKind::LengthValue, _) => PsdError::LayerRecordLengthMismatch { offset, len: error.input.len() },
+            ("image data", ErrorKind::MapRes, _) => PsdError::UnknownCompression { value: u16_at(offset), offset },
+            ("image data", ErrorKind::TooLarge, _) => PsdError::TooLarge,
+            _ => PsdError::Malformed { section, offset },
         }
     }
 }
 
-pub fn parse_psd(input: &[u8]) -> Result<Psd, anyhow::Error> {
-    let (input, header) = parse_header(input).map_err(|e| e.map_input(|slice| slice.to_vec()))?;
-    let (input, color_mode) = parse_color_mode(input, &header).map_err(|e| e.map_input(|slice| slice.to_vec()))?;
-    let (input, image_resources) = parse_image_resources(input).map_err(|e| e.map_input(|slice| slice.to_vec()))?;
-    let (input, layer_information) = parse_layer_and_mask_information(input).map_err(|e| e.map_input(|slice| slice.to_vec()))?;
-    let (_, image_data) = parse_image_data(input, &header).map_err(|e| e.map_input(|slice| slice.to_vec()))?;
-    Ok(Psd { header, color_mode, image_resources, layer_information, image_data })
+fn parse_psd_inner<'a>(input: &'a [u8], options: &ParseOptions) -> Result<(&'a [u8], Psd<'a>), PsdError> {
+    let whole = input;
+    let (input, header) = parse_header(input, options).map_err(section_error(whole, "header"))?;
+    let (input, color_mode) = parse_color_mode(input, &header).map_err(section_error(whole, "color mode data"))?;
+    let (input, image_resources) = parse_image_resources(input, options).map_err(section_error(whole, "image resources"))?;
+    let (input, layer_information) = parse_layer_and_mask_information(input, &header, options).map_err(section_error(whole, "layer and mask information"))?;
+    let (input, image_data) = parse_image_data(input, &header).map_err(section_error(whole, "image data"))?;
+    Ok((input, Psd { header, color_mode, image_resources, layer_information, image_data }))
 }
@@ -0,0 +1,120 @@
+use std::borrow::Cow;
+use std::convert::{TryFrom, TryInto};
+
+use nom::bytes::complete::take;
+use nom::combinator::map_res;
+use nom::number::complete::{be_u32, be_u64, be_u8};
+use nom::IResult;
+
+use crate::descriptor::parse_versioned_descriptor;
+use crate::owned_len;
+use crate::strings::parse_unicode_string;
+
+/// How the source of a [`LinkedFile`] is kept.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum LinkedFileKind {
+    /// `liFD`, the bytes of the file are embedded in the document.
+    Data,
+    /// `liFE`, the file is linked by its path.
+    External,
+    /// `liFA`, the file is linked through an alias.
+    Alias,
+}
+
+impl LinkedFileKind {
+    fn from_key(key: &[u8]) -> Result<LinkedFileKind, ()> {
+        match key {
+            b"liFD" => Ok(LinkedFileKind::Data),
+            b"liFE" => Ok(LinkedFileKind::External),
+            b"liFA" => Ok(LinkedFileKind::Alias),
+            _ => Err(()),
+        }
+    }
+}
+
+/// The source of a smart object, from the `lnkD` (or `lnk2`, `lnk3`) block of the document, referenced by placed layers through its unique id.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct LinkedFile<'a> {
+    kind: LinkedFileKind,
+    version: u32,
+    unique_id: String,
+    file_name: String,
+    #[cfg_attr(feature = "serde", serde(serialize_with = "crate::serialize::text"))]
+    file_type: [u8; 4],
+    #[cfg_attr(feature = "serde", serde(serialize_with = "crate::serialize::option_len"))]
+    data: Option<Cow<'a, [u8]>>,
+}
+
+impl<'a> LinkedFile<'a> {
+    pub fn kind(&self) -> LinkedFileKind {
+        self.kind
+    }
+    pub fn version(&self) -> u32 {
+        self.version
+    }
+    /// The id placed layers refer to as `Idnt`.
+    pub fn unique_id(&self) -> &str {
+        &self.unique_id
+    }
+    /// The name of the file when it was placed.
+    pub fn file_name(&self) -> &str {
+        &self.file_name
+    }
+    /// The Mac OS file type, such as `png ` or `8BPS`, spaces when the writer didn't record one.
+    pub fn file_type(&self) -> &[u8; 4] {
+        &self.file_type
+    }
+    /// The bytes of the embedded file. `None` for a linked file, whose bytes aren't in the document.
+    pub fn data(&self) -> Option<&[u8]> {
+        self.data.as_deref()
+    }
+    pub(crate) fn memory_footprint(&self) -> usize {
+        self.data.as_ref().map_or(0, owned_len)
+    }
+    pub(crate) fn into_static(self) -> LinkedFile<'static> {
+        let LinkedFile { kind, version, unique_id, file_name, file_type, data } = self;
+        LinkedFile { kind, version, unique_id, file_name, file_type, data: data.map(|data| Cow::Owned(data.into_owned())) }
+    }
+}
+
+fn parse_linked_file(input: &[u8]) -> IResult<&[u8], LinkedFile<'_>> {
+    let (input, kind) = map_res(take(4usize), LinkedFileKind::from_key)(input)?;
+    let (input, version) = be_u32(input)?;
+    let (input, id_len) = be_u8(input)?;
+    let (input, id) = take(id_len)(input)?;
+    let (input, file_name) = parse_unicode_string(input)?;
+    let (input, file_type) = take(4usize)(input)?;
+    let (input, _creator) = take(4usize)(input)?;
+    // a length past the address space can't be in memory, so it fails rather than being truncated
+    let (input, len) = map_res(be_u64, usize::try_from)(input)?;
+    let (mut input, open_descriptor) = be_u8(input)?;
+    if open_descriptor != 0 {
+        let (i, _version) = be_u32(input)?;
+        let (i, _open_parameters) = parse_versioned_descriptor(i)?;
+        input = i;
+    }
+    // only embedded files carry their bytes, the fields after them are left out
+    let data = match kind {
+        LinkedFileKind::Data => Some(Cow::Borrowed(take(len)(input)?.1)),
+        LinkedFileKind::External | LinkedFileKind::Alias => None,
+    };
+    Ok((
+        &input[..0],
+        LinkedFile { kind, version, unique_id: String::from_utf8_lossy(id).into_owned(), file_name, file_type: file_type.try_into().unwrap(), data },
+    ))
+}
+
+/// Parses the files of a `lnkD` block, each one with its 8-byte length in front and padded to a multiple of 4 bytes.
+pub(crate) fn parse_linked_files(mut input: &[u8]) -> IResult<&[u8], Vec<LinkedFile<'_>>> {
+    let mut files = Vec::new();
+    while input.len() >= 8 {
+        let (i, len) = map_res(be_u64, usize::try_from)(input)?;
+        let (_, file) = take(len)(i)?;
+        let (_, file) = parse_linked_file(file)?;
+        files.push(file);
+        input = i.get(len.next_multiple_of(4)..).unwrap_or(&[]);
+    }
+    Ok((input, files))
+}
@@ -0,0 +1,217 @@
+use nom::bytes::complete::take;
+use nom::number::complete::{be_i32, be_u16, be_u32};
+use nom::IResult;
+
+use crate::descriptor::Descriptor;
+use crate::fixed::fixed_8_24;
+
+/// A point of a path as `[x, y]`, relative to the document size: `[0.0, 0.0]` is the top left and `[1.0, 1.0]` the bottom right.
+pub type PathPoint = [f64; 2];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct BezierKnot {
+    linked: bool,
+    preceding: PathPoint,
+    anchor: PathPoint,
+    leaving: PathPoint,
+}
+
+impl BezierKnot {
+    /// Whether the control points move together in Photoshop.
+    pub fn linked(&self) -> bool {
+        self.linked
+    }
+    /// Control point for the segment arriving at this knot.
+    pub fn preceding(&self) -> PathPoint {
+        self.preceding
+    }
+    pub fn anchor(&self) -> PathPoint {
+        self.anchor
+    }
+    /// Control point for the segment leaving this knot.
+    pub fn leaving(&self) -> PathPoint {
+        self.leaving
+    }
+}
+
+/// One subpath, a length record followed by its bezier knot records.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct PathRecord {
+    closed: bool,
+    knots: Vec<BezierKnot>,
+}
+
+fn svg_number(value: f64) -> String {
+    let value = (value * 1000.0).round() / 1000.0;
+    // avoid "-0"
+    format!("{}", if value == 0.0 { 0.0 } else { value })
+}
+
+impl PathRecord {
+    pub fn closed(&self) -> bool {
+        self.closed
+    }
+    pub fn knots(&self) -> &[BezierKnot] {
+        &self.knots
+    }
+    /// The subpath as SVG path data (`M`, `C` and, for a closed subpath, `Z` commands) in the pixels of a `width` x `height` document.
+    pub fn to_svg(&self, width: u32, height: u32) -> String {
+        let point = |[x, y]: PathPoint| format!("{} {}", svg_number(x * width as f64), svg_number(y * height as f64));
+        let mut svg = match self.knots.first() {
+            Some(first) => format!("M {}", point(first.anchor)),
+            None => return String::new(),
+        };
+        let segments = if self.closed { self.knots.len() } else { self.knots.len() - 1 };
+        for index in 0..segments {
+            let from = &self.knots[index];
+            let to = &self.knots[(index + 1) % self.knots.len()];
+            svg += &format!(" C {} {} {}", point(from.leaving), point(to.preceding), point(to.anchor));
+        }
+        if self.closed {
+            svg += " Z";
+        }
+        svg
+    }
+}
+
+/// A path as stored in the path resources and vector masks.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Path {
+    records: Vec<PathRecord>,
+    initial_fill: bool,
+}
+
+impl Path {
+    pub fn records(&self) -> &[PathRecord] {
+        &self.records
+    }
+    /// Whether the path starts with all pixels filled, from the initial fill rule record.
+    pub fn initial_fill(&self) -> bool {
+        self.initial_fill
+    }
+    /// All subpaths as one SVG `d` attribute, see [`PathRecord::to_svg`].
+    pub fn to_svg_path(&self, width: u32, height: u32) -> String {
+        self.records.iter().map(|record| record.to_svg(width, height)).filter(|svg| !svg.is_empty()).collect::<Vec<_>>().join(" ")
+    }
+}
+
+fn parse_point(input: &[u8]) -> IResult<&[u8], PathPoint> {
+    // vertical component first
+    let (input, y) = be_i32(input)?;
+    let (input, x) = be_i32(input)?;
+    Ok((input, [fixed_8_24(x), fixed_8_24(y)]))
+}
+
+/// Parses a sequence of 26-byte path records.
+pub(crate) fn parse_path(mut input: &[u8]) -> IResult<&[u8], Path> {
+    let mut records = Vec::new();
+    let mut initial_fill = false;
+    while input.len() >= 26 {
+        let (follow, record) = take(26usize)(input)?;
+        let (data, selector) = be_u16(record)?;
+        match selector {
+            0 | 3 => records.push(PathRecord { closed: selector == 0, knots: Vec::new() }),
+            1 | 2 | 4 | 5 => {
+                let (data, preceding) = parse_point(data)?;
+                let (data, anchor) = parse_point(data)?;
+                let (_, leaving) = parse_point(data)?;
+                let knot = BezierKnot { linked: selector == 1 || selector == 4, preceding, anchor, leaving };
+                // a knot before any subpath length record belongs to no subpath and is skipped
+                if let Some(record) = records.last_mut() {
+                    record.knots.push(knot);
+                }
+            }
+            8 => {
+                let (_, fill) = be_u16(data)?;
+                initial_fill = fill == 1;
+            }
+            // path fill rule and clipboard records carry nothing needed to draw the path
+            _ => {}
+        }
+        input = follow;
+    }
+    Ok((input, Path { records, initial_fill }))
+}
+
+bitflags::bitflags! {
+    pub struct VectorMaskFlags : u32 {
+        const INVERT = 0b0000_0001;
+        const NOT_LINK = 0b0000_0010;
+        const DISABLE = 0b0000_0100;
+    }
+}
+
+/// The vector mask of a layer, from the `vsms` or `vmsk` additional layer information.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct VectorMask {
+    flags: VectorMaskFlags,
+    path: Path,
+}
+
+impl VectorMask {
+    pub fn flags(&self) -> VectorMaskFlags {
+        self.flags
+    }
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+pub(crate) fn parse_vector_mask(input: &[u8]) -> IResult<&[u8], VectorMask> {
+    let (input, _version) = be_u32(input)?;
+    let (input, flags) = be_u32(input)?;
+    let (input, path) = parse_path(input)?;
+    Ok((input, VectorMask { flags: VectorMaskFlags::from_bits_truncate(flags), path }))
+}
+
+/// A shape of a shape layer as Photoshop edits it, with the parameters of a live shape from the vector origination data (`vogk`).
+/// Bounds are `[top, left, bottom, right]` in pixels.
+#[derive(Debug, Clone, PartialEq)]
+pub enum VectorShape {
+    Rect { bounds: [f64; 4] },
+    /// `radii` of the top left, top right, bottom right and bottom left corners.
+    RoundedRect { bounds: [f64; 4], radii: [f64; 4] },
+    Ellipse { bounds: [f64; 4] },
+    /// Anything without parameters, such as a custom shape or a drawn path, given by the path of the vector mask.
+    Custom(Path),
+}
+
+/// The shapes of the vector origination data, from `keyDescriptorList`. Entries of other kinds (lines, polygons, custom shapes) add the path of
+/// `mask` as a single [`VectorShape::Custom`], as does a layer that has a vector mask but no origination data.
+pub(crate) fn vector_shapes(origination: Option<&Descriptor>, mask: Option<&VectorMask>) -> Vec<VectorShape> {
+    let entries = origination.and_then(|origination| origination.get("keyDescriptorList")).and_then(|list| list.as_list()).unwrap_or(&[]);
+    let rect = |descriptor: &Descriptor, key: &str, fields: [&str; 4]| -> Option<[f64; 4]> {
+        let rect = descriptor.get(key)?.as_descriptor()?;
+        let mut values = [0.0; 4];
+        for (value, field) in values.iter_mut().zip(fields) {
+            *value = rect.get(field)?.as_f64()?;
+        }
+        Some(values)
+    };
+    let mut shapes = Vec::new();
+    let mut custom = entries.is_empty();
+    for entry in entries.iter().filter_map(|entry| entry.as_descriptor()) {
+        let bounds = rect(entry, "keyOriginShapeBBox", ["Top ", "Left", "Btom", "Rght"]);
+        let shape = match (entry.get("keyOriginType").and_then(|kind| kind.as_i64()), bounds) {
+            (Some(1), Some(bounds)) => Some(VectorShape::Rect { bounds }),
+            (Some(2), Some(bounds)) => {
+                let radii = rect(entry, "keyOriginRRectRadii", ["topLeft", "topRight", "bottomRight", "bottomLeft"]).unwrap_or([0.0; 4]);
+                Some(VectorShape::RoundedRect { bounds, radii })
+            }
+            (Some(5), Some(bounds)) => Some(VectorShape::Ellipse { bounds }),
+            _ => None,
+        };
+        match shape {
+            Some(shape) => shapes.push(shape),
+            None => custom = true,
+        }
+    }
+    if let (true, Some(mask)) = (custom, mask) {
+        shapes.push(VectorShape::Custom(mask.path.clone()));
+    }
+    shapes
+}
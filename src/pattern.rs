@@ -0,0 +1,235 @@
+use std::borrow::Cow;
+
+use nom::bytes::complete::take;
+use nom::combinator::map_res;
+use nom::number::complete::{be_u16, be_u32, be_u8};
+use nom::IResult;
+use once_cell::sync::OnceCell;
+
+use crate::header::ColorMode;
+use crate::inflate::decompress_zip;
+use crate::error::PsdError;
+use crate::layer_info::{unpack_bits, ImageCompression};
+use crate::{owned_len, plane_len};
+use crate::strings::parse_unicode_string;
+
+/// A pattern defined in the `Patt` (or `Pat2`, `Pat3`) block of the document, referenced by pattern fills and overlays through its id.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Pattern<'a> {
+    id: String,
+    name: String,
+    color_mode: ColorMode,
+    width: u32,
+    height: u32,
+    #[cfg_attr(feature = "serde", serde(serialize_with = "crate::serialize::option_len"))]
+    palette: Option<Cow<'a, [u8]>>,
+    channels: Vec<PatternChannel<'a>>,
+}
+
+impl<'a> Pattern<'a> {
+    /// The unique id, which references to the pattern store as `Idnt`.
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+    pub fn color_mode(&self) -> ColorMode {
+        self.color_mode
+    }
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+    /// The 256 RGB entries of the color table of an indexed pattern.
+    pub fn palette(&self) -> Option<&[u8]> {
+        self.palette.as_deref()
+    }
+    /// The channels of the pattern in channel order, followed by its user mask and sheet mask. Channels not written are left out.
+    pub fn channels(&self) -> &[PatternChannel<'a>] {
+        &self.channels
+    }
+    pub(crate) fn memory_footprint(&self) -> usize {
+        self.palette.as_ref().map_or(0, owned_len) + self.channels.iter().map(|channel| owned_len(&channel.data) + channel.raw_data.get().map_or(0, owned_len)).sum::<usize>()
+    }
+    pub(crate) fn into_static(self, decode: bool) -> Pattern<'static> {
+        let Pattern { id, name, color_mode, width, height, palette, channels } = self;
+        Pattern {
+            id,
+            name,
+            color_mode,
+            width,
+            height,
+            palette: palette.map(|palette| Cow::Owned(palette.into_owned())),
+            channels: channels.into_iter().map(|channel| channel.into_static(decode)).collect(),
+        }
+    }
+}
+
+/// A channel of a [`Pattern`], stored in a virtual memory array.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct PatternChannel<'a> {
+    index: usize,
+    top: u32,
+    left: u32,
+    bottom: u32,
+    right: u32,
+    depth: u16,
+    compression: ImageCompression,
+    #[cfg_attr(feature = "serde", serde(serialize_with = "crate::serialize::len"))]
+    data: Cow<'a, [u8]>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    raw_data: OnceCell<Cow<'a, [u8]>>,
+}
+
+impl<'a> PatternChannel<'a> {
+    /// The position among the channels of the pattern, the color channels coming first, then the user mask and the sheet mask.
+    pub fn index(&self) -> usize {
+        self.index
+    }
+    pub fn top(&self) -> u32 {
+        self.top
+    }
+    pub fn left(&self) -> u32 {
+        self.left
+    }
+    pub fn bottom(&self) -> u32 {
+        self.bottom
+    }
+    pub fn right(&self) -> u32 {
+        self.right
+    }
+    pub fn width(&self) -> u32 {
+        self.right.saturating_sub(self.left)
+    }
+    pub fn height(&self) -> u32 {
+        self.bottom.saturating_sub(self.top)
+    }
+    pub fn depth(&self) -> u16 {
+        self.depth
+    }
+    pub fn compression(&self) -> ImageCompression {
+        self.compression
+    }
+    /// The decompressed channel, row by row. Panics when the data can't be decompressed, see [`try_raw_data`](Self::try_raw_data).
+    pub fn raw_data(&self) -> &[u8] {
+        self.try_raw_data().unwrap_or_else(|error| panic!("{}", error))
+    }
+    /// [`raw_data`](Self::raw_data), failing for zip compressed data without the `zip` feature, when the data doesn't decompress to the size of the channel,
+    /// and for a channel too large for the target.
+    pub fn try_raw_data(&self) -> Result<&[u8], PsdError> {
+        let raw_data = self.raw_data.get_or_try_init(|| match self.compression {
+            ImageCompression::Raw => Ok(match &self.data {
+                Cow::Borrowed(data) => Cow::Borrowed(*data),
+                Cow::Owned(data) => Cow::Owned(data.clone()),
+            }),
+            ImageCompression::RLE => {
+                let rows = self.height() as usize;
+                let len = plane_len(self.width(), self.height(), self.depth, 1)?;
+                Ok(Cow::Owned(unpack_bits(self.data.get(rows * 2..).unwrap_or(&[]), len)?))
+            }
+            compression @ (ImageCompression::ZipWithoutPrediction | ImageCompression::ZipWithPrediction) => {
+                decompress_zip(&self.data, compression, self.width(), self.height(), self.depth, 1).map(Cow::Owned)
+            }
+        })?;
+        Ok(raw_data)
+    }
+    /// With `decode`, data that can't be decoded is kept compressed, failing again when it is used.
+    fn into_static(self, decode: bool) -> PatternChannel<'static> {
+        if decode {
+            let _ = self.try_raw_data();
+        }
+        let PatternChannel { index, top, left, bottom, right, depth, compression, data, raw_data } = self;
+        let raw_data_cell = OnceCell::new();
+        if let Some(raw_data) = raw_data.into_inner() {
+            raw_data_cell.set(Cow::Owned(raw_data.into_owned())).unwrap();
+        }
+        PatternChannel {
+            index,
+            top,
+            left,
+            bottom,
+            right,
+            depth,
+            compression,
+            data: Cow::Owned(data.into_owned()),
+            raw_data: raw_data_cell,
+        }
+    }
+}
+
+fn parse_rect(input: &[u8]) -> IResult<&[u8], [u32; 4]> {
+    let (input, top) = be_u32(input)?;
+    let (input, left) = be_u32(input)?;
+    let (input, bottom) = be_u32(input)?;
+    let (input, right) = be_u32(input)?;
+    Ok((input, [top, left, bottom, right]))
+}
+
+fn parse_pattern(input: &[u8]) -> IResult<&[u8], Pattern<'_>> {
+    let (input, _version) = be_u32(input)?;
+    let (input, color_mode) = map_res(be_u32, |mode| ColorMode::from_u16(mode as u16))(input)?;
+    let (input, height) = be_u16(input)?;
+    let (input, width) = be_u16(input)?;
+    let (input, name) = parse_unicode_string(input)?;
+    let (input, id_len) = be_u8(input)?;
+    let (input, id) = take(id_len)(input)?;
+    let (input, palette) = match color_mode {
+        ColorMode::Indexed => {
+            let (input, palette) = take(768usize)(input)?;
+            (input, Some(Cow::Borrowed(palette)))
+        }
+        _ => (input, None),
+    };
+    // the virtual memory array list
+    let (input, _version) = be_u32(input)?;
+    let (input, len) = be_u32(input)?;
+    let (_, list) = take(len)(input)?;
+    let (list, _rect) = parse_rect(list)?;
+    let (mut list, channel_count) = be_u32(list)?;
+    let mut channels = Vec::new();
+    // each color channel, then the user mask and the sheet mask
+    for index in 0..channel_count as usize + 2 {
+        if list.is_empty() {
+            break;
+        }
+        let (i, written) = be_u32(list)?;
+        list = i;
+        if written == 0 {
+            continue;
+        }
+        let (i, len) = be_u32(list)?;
+        if len == 0 {
+            list = i;
+            continue;
+        }
+        let (i, array) = take(len)(i)?;
+        list = i;
+        let (array, _depth) = be_u32(array)?;
+        let (array, [top, left, bottom, right]) = parse_rect(array)?;
+        let (array, depth) = be_u16(array)?;
+        let (data, compression) = map_res(be_u8, |compression| ImageCompression::from_u16(compression as u16))(array)?;
+        channels.push(PatternChannel { index, top, left, bottom, right, depth, compression, data: Cow::Borrowed(data), raw_data: OnceCell::new() });
+    }
+    Ok((
+        &input[len as usize..],
+        Pattern { id: String::from_utf8_lossy(id).into_owned(), name, color_mode, width: width as u32, height: height as u32, palette, channels },
+    ))
+}
+
+/// Parses the patterns of a `Patt` block, each one with its length in front and padded to a multiple of 4 bytes.
+pub(crate) fn parse_patterns(mut input: &[u8]) -> IResult<&[u8], Vec<Pattern<'_>>> {
+    let mut patterns = Vec::new();
+    while input.len() >= 4 {
+        let (i, len) = be_u32(input)?;
+        let (_, pattern) = take(len)(i)?;
+        let (_, pattern) = parse_pattern(pattern)?;
+        patterns.push(pattern);
+        input = i.get((len as usize).next_multiple_of(4)..).unwrap_or(&[]);
+    }
+    Ok((input, patterns))
+}
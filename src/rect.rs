@@ -0,0 +1,58 @@
+/// A rectangle in document pixels, `top` and `left` inclusive, `bottom` and `right` exclusive, in the order the format stores them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Rect {
+    top: i32,
+    left: i32,
+    bottom: i32,
+    right: i32,
+}
+
+impl Rect {
+    pub fn new(top: i32, left: i32, bottom: i32, right: i32) -> Self {
+        Rect { top, left, bottom, right }
+    }
+    /// The rectangle of the given size with its top left corner at `(left, top)`.
+    pub fn from_size(left: i32, top: i32, width: u32, height: u32) -> Self {
+        Rect { top, left, bottom: top + height as i32, right: left + width as i32 }
+    }
+    pub fn top(&self) -> i32 {
+        self.top
+    }
+    pub fn left(&self) -> i32 {
+        self.left
+    }
+    pub fn bottom(&self) -> i32 {
+        self.bottom
+    }
+    pub fn right(&self) -> i32 {
+        self.right
+    }
+    /// 0 when `right` isn't past `left`.
+    pub fn width(&self) -> u32 {
+        (self.right as i64 - self.left as i64).max(0) as u32
+    }
+    /// 0 when `bottom` isn't past `top`.
+    pub fn height(&self) -> u32 {
+        (self.bottom as i64 - self.top as i64).max(0) as u32
+    }
+    pub fn is_empty(&self) -> bool {
+        self.width() == 0 || self.height() == 0
+    }
+    pub fn contains(&self, x: i32, y: i32) -> bool {
+        (self.left..self.right).contains(&x) && (self.top..self.bottom).contains(&y)
+    }
+    /// The overlap of both rectangles, `None` when they don't overlap.
+    pub fn intersection(&self, other: &Rect) -> Option<Rect> {
+        let rect = Rect {
+            top: self.top.max(other.top),
+            left: self.left.max(other.left),
+            bottom: self.bottom.min(other.bottom),
+            right: self.right.min(other.right),
+        };
+        if rect.is_empty() {
+            None
+        } else {
+            Some(rect)
+        }
+    }
+}
@@ -0,0 +1,43 @@
+//! `Serialize` for the document tree with the `serde` feature. Byte buffers such as channel data and unparsed blocks are written as their
+//! length, so the output describes the structure of a document without its pixels, names and keys are written as text and bit flags as their bits.
+
+use std::borrow::Cow;
+
+use serde::{Serialize, Serializer};
+
+use crate::layer_info::{LayerMaskFlags, LayerRecordFlags, ProtectionFlags};
+use crate::path::VectorMaskFlags;
+
+// `serialize_with` passes a reference to the field, which is a `Cow`
+#[allow(clippy::ptr_arg)]
+pub(crate) fn len<S: Serializer>(data: &Cow<[u8]>, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_u64(data.len() as u64)
+}
+
+pub(crate) fn option_len<S: Serializer>(data: &Option<Cow<[u8]>>, serializer: S) -> Result<S::Ok, S::Error> {
+    data.as_ref().map(|data| data.len() as u64).serialize(serializer)
+}
+
+/// Bytes holding text in some 8-bit encoding (a layer name, a 4-byte key), decoded lossily as UTF-8.
+pub(crate) fn text<S: Serializer, T: AsRef<[u8]>>(data: &T, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&String::from_utf8_lossy(data.as_ref()))
+}
+
+#[allow(clippy::ptr_arg)]
+pub(crate) fn key<S: Serializer>(key: &Cow<[u8; 4]>, serializer: S) -> Result<S::Ok, S::Error> {
+    text(&**key, serializer)
+}
+
+macro_rules! serialize_bits {
+    ($($flags:ty),*) => {
+        $(
+            impl Serialize for $flags {
+                fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                    self.bits().serialize(serializer)
+                }
+            }
+        )*
+    };
+}
+
+serialize_bits!(LayerMaskFlags, LayerRecordFlags, ProtectionFlags, VectorMaskFlags);
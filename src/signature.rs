@@ -0,0 +1,31 @@
+use nom::bytes::complete::take;
+use nom::error::{Error, ErrorKind};
+use nom::IResult;
+
+use crate::ParseOptions;
+
+pub(crate) const PSD: &[u8; 4] = b"8BPS";
+pub(crate) const BIM: &[u8; 4] = b"8BIM";
+pub(crate) const B64: &[u8; 4] = b"8B64";
+
+/// Consumes a 4-byte signature that has to be one of `expected`.
+///
+/// With `strict_signatures` a mismatch is a parse error, otherwise the parser continues as if the signature were correct.
+pub(crate) fn signature<'a>(input: &'a [u8], expected: &[&[u8; 4]], options: &ParseOptions) -> IResult<&'a [u8], &'a [u8]> {
+    let (follow, found) = take(4usize)(input)?;
+    if expected.iter().any(|signature| &signature[..] == found) {
+        return Ok((follow, found));
+    }
+    if options.strict_signatures {
+        return Err(nom::Err::Error(Error::new(input, ErrorKind::Tag)));
+    }
+    Ok((follow, found))
+}
+
+/// The offset of the next occurrence of `sig` in `input`, where a parser that lost track of the block boundaries can resume.
+pub(crate) fn find_next_signature(input: &[u8], sig: &[u8]) -> Option<usize> {
+    if sig.is_empty() {
+        return Some(0);
+    }
+    input.windows(sig.len()).position(|window| window == sig)
+}
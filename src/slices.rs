@@ -0,0 +1,237 @@
+use nom::number::complete::{be_i32, be_u32, be_u8};
+use nom::IResult;
+
+use crate::descriptor::{parse_versioned_descriptor, Descriptor};
+use crate::strings::parse_unicode_string;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SliceOrigin {
+    AutoGenerated,
+    LayerGenerated,
+    UserGenerated,
+}
+
+impl SliceOrigin {
+    fn from_u32(value: u32) -> Self {
+        match value {
+            1 => SliceOrigin::LayerGenerated,
+            2 => SliceOrigin::UserGenerated,
+            _ => SliceOrigin::AutoGenerated,
+        }
+    }
+    fn from_key(key: &str) -> Self {
+        match key {
+            "layerGenerated" => SliceOrigin::LayerGenerated,
+            "userGenerated" => SliceOrigin::UserGenerated,
+            _ => SliceOrigin::AutoGenerated,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Slice {
+    id: u32,
+    group_id: u32,
+    origin: SliceOrigin,
+    layer_id: Option<u32>,
+    name: String,
+    top: i32,
+    left: i32,
+    bottom: i32,
+    right: i32,
+    url: String,
+    target: String,
+    message: String,
+    alt_text: String,
+}
+
+impl Slice {
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+    pub fn group_id(&self) -> u32 {
+        self.group_id
+    }
+    pub fn origin(&self) -> SliceOrigin {
+        self.origin
+    }
+    /// The layer a layer-based slice was generated from.
+    pub fn layer_id(&self) -> Option<u32> {
+        self.layer_id
+    }
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+    pub fn top(&self) -> i32 {
+        self.top
+    }
+    pub fn left(&self) -> i32 {
+        self.left
+    }
+    pub fn bottom(&self) -> i32 {
+        self.bottom
+    }
+    pub fn right(&self) -> i32 {
+        self.right
+    }
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+    pub fn target(&self) -> &str {
+        &self.target
+    }
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+    pub fn alt_text(&self) -> &str {
+        &self.alt_text
+    }
+}
+
+/// Slices (resource 1050).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Slices {
+    version: u32,
+    name: String,
+    top: i32,
+    left: i32,
+    bottom: i32,
+    right: i32,
+    slices: Vec<Slice>,
+}
+
+impl Slices {
+    /// 6 for the binary layout, 7 and 8 for the descriptor based one.
+    pub fn version(&self) -> u32 {
+        self.version
+    }
+    /// Name of the slice group.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+    pub fn top(&self) -> i32 {
+        self.top
+    }
+    pub fn left(&self) -> i32 {
+        self.left
+    }
+    pub fn bottom(&self) -> i32 {
+        self.bottom
+    }
+    pub fn right(&self) -> i32 {
+        self.right
+    }
+    pub fn slices(&self) -> &[Slice] {
+        &self.slices
+    }
+}
+
+pub(crate) fn parse_slices(input: &[u8]) -> IResult<&[u8], Option<Slices>> {
+    let (input, version) = be_u32(input)?;
+    match version {
+        6 => {
+            let (input, slices) = parse_binary_slices(input)?;
+            Ok((input, Some(slices)))
+        }
+        7 | 8 => {
+            let (input, descriptor) = parse_versioned_descriptor(input)?;
+            Ok((input, slices_from_descriptor(version, &descriptor)))
+        }
+        _ => Ok((input, None)),
+    }
+}
+
+fn parse_rect(input: &[u8]) -> IResult<&[u8], [i32; 4]> {
+    let (input, top) = be_i32(input)?;
+    let (input, left) = be_i32(input)?;
+    let (input, bottom) = be_i32(input)?;
+    let (input, right) = be_i32(input)?;
+    Ok((input, [top, left, bottom, right]))
+}
+
+fn parse_binary_slices(input: &[u8]) -> IResult<&[u8], Slices> {
+    let (input, [top, left, bottom, right]) = parse_rect(input)?;
+    let (input, name) = parse_unicode_string(input)?;
+    let (mut input, count) = be_u32(input)?;
+    let mut slices = Vec::new();
+    for _ in 0..count {
+        let (i, id) = be_u32(input)?;
+        let (i, group_id) = be_u32(i)?;
+        let (i, origin) = be_u32(i)?;
+        let (i, layer_id) = if origin == 1 {
+            let (i, layer_id) = be_u32(i)?;
+            (i, Some(layer_id))
+        } else {
+            (i, None)
+        };
+        let (i, name) = parse_unicode_string(i)?;
+        let (i, _slice_type) = be_u32(i)?;
+        // bounds are stored left, top, right, bottom here
+        let (i, left) = be_i32(i)?;
+        let (i, top) = be_i32(i)?;
+        let (i, right) = be_i32(i)?;
+        let (i, bottom) = be_i32(i)?;
+        let (i, url) = parse_unicode_string(i)?;
+        let (i, target) = parse_unicode_string(i)?;
+        let (i, message) = parse_unicode_string(i)?;
+        let (i, alt_text) = parse_unicode_string(i)?;
+        let (i, _cell_text_is_html) = be_u8(i)?;
+        let (i, _cell_text) = parse_unicode_string(i)?;
+        let (i, _horizontal_alignment) = be_u32(i)?;
+        let (i, _vertical_alignment) = be_u32(i)?;
+        let (i, _argb) = be_u32(i)?;
+        slices.push(Slice {
+            id,
+            group_id,
+            origin: SliceOrigin::from_u32(origin),
+            layer_id,
+            name,
+            top,
+            left,
+            bottom,
+            right,
+            url,
+            target,
+            message,
+            alt_text,
+        });
+        input = i;
+    }
+    Ok((input, Slices { version: 6, name, top, left, bottom, right, slices }))
+}
+
+fn bounds(descriptor: Option<&Descriptor>) -> [i32; 4] {
+    let value = |key: &str| descriptor.and_then(|bounds| bounds.get(key)).and_then(|value| value.as_f64()).unwrap_or(0.0) as i32;
+    [value("Top "), value("Left"), value("Btom"), value("Rght")]
+}
+
+fn slices_from_descriptor(version: u32, descriptor: &Descriptor) -> Option<Slices> {
+    let string = |descriptor: &Descriptor, key: &str| descriptor.get(key).and_then(|value| value.as_str()).unwrap_or("").to_string();
+    let [top, left, bottom, right] = bounds(descriptor.get("bounds").and_then(|value| value.as_descriptor()));
+    let slices = descriptor
+        .get("slices")?
+        .as_list()?
+        .iter()
+        .filter_map(|slice| slice.as_descriptor())
+        .map(|slice| {
+            let integer = |key: &str| slice.get(key).and_then(|value| value.as_i64()).map(|value| value as u32);
+            let [top, left, bottom, right] = bounds(slice.get("bounds").and_then(|value| value.as_descriptor()));
+            Slice {
+                id: integer("sliceID").unwrap_or(0),
+                group_id: integer("groupID").unwrap_or(0),
+                origin: slice.get("origin").and_then(|value| value.as_enum()).map_or(SliceOrigin::AutoGenerated, SliceOrigin::from_key),
+                layer_id: integer("layerID"),
+                name: string(slice, "Nm  "),
+                top,
+                left,
+                bottom,
+                right,
+                url: string(slice, "url "),
+                target: string(slice, "null"),
+                message: string(slice, "Msge"),
+                alt_text: string(slice, "altTag"),
+            }
+        })
+        .collect();
+    Some(Slices { version, name: string(descriptor, "baseName"), top, left, bottom, right, slices })
+}
@@ -0,0 +1,13 @@
+use nom::combinator::map_res;
+use nom::multi::length_count;
+use nom::number::complete::{be_u16, be_u32};
+use nom::IResult;
+
+pub(crate) fn parse_unicode_string(input: &[u8]) -> IResult<&[u8], String> {
+    let (input, mut string) = map_res(length_count(be_u32, be_u16), |units| String::from_utf16(&units))(input)?;
+    // some writers count a terminating NUL as a part of the string
+    while string.ends_with('\0') {
+        string.pop();
+    }
+    Ok((input, string))
+}
@@ -0,0 +1,74 @@
+use nom::number::complete::{be_f64, be_u16};
+use nom::IResult;
+
+use crate::descriptor::parse_versioned_descriptor;
+use crate::engine_data::{parse_engine_data, EngineValue};
+
+/// The type tool data of a text layer (`TySh`).
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct TextLayer {
+    transform: [f64; 6],
+    text: String,
+    engine_data: Option<EngineValue>,
+}
+
+impl TextLayer {
+    /// The affine transform of the text as `[xx, xy, yx, yy, tx, ty]`.
+    pub fn transform(&self) -> [f64; 6] {
+        self.transform
+    }
+    /// The content of the text layer, paragraphs separated by `'\r'`.
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+    /// The EngineData of the text, holding the style runs and the paragraph runs with their style sheets, and the style sheets and fonts they refer to
+    /// in `ResourceDict`. `None` when the layer has none, or it can't be parsed.
+    pub fn engine_data(&self) -> Option<&EngineValue> {
+        self.engine_data.as_ref()
+    }
+    /// PostScript names of the fonts the text is set in, one for each font referenced by a style run.
+    pub fn fonts(&self) -> Vec<String> {
+        let engine_data = match &self.engine_data {
+            Some(engine_data) => engine_data,
+            None => return Vec::new(),
+        };
+        let resources = engine_data.get("ResourceDict");
+        let font_set = resources.and_then(|resources| resources.get("FontSet")).and_then(|fonts| fonts.as_array()).unwrap_or(&[]);
+        let style_font = |style: &EngineValue| style.get("StyleSheetData").and_then(|data| data.get("Font")).and_then(|font| font.as_i64());
+        // runs without a font of their own use the normal style sheet
+        let default_font = resources.and_then(|resources| {
+            let index = resources.get("TheNormalStyleSheet")?.as_i64()?;
+            style_font(resources.get("StyleSheetSet")?.as_array()?.get(index as usize)?)
+        });
+        let runs = engine_data.get("EngineDict").and_then(|engine| engine.get("StyleRun")).and_then(|run| run.get("RunArray")).and_then(|runs| runs.as_array()).unwrap_or(&[]);
+        let mut fonts = Vec::new();
+        for run in runs {
+            let index = run.get("StyleSheet").and_then(style_font).or(default_font);
+            let name = index.and_then(|index| font_set.get(index as usize)).and_then(|font| font.get("Name")).and_then(|name| name.as_str());
+            if let Some(name) = name {
+                if !fonts.iter().any(|font| font == name) {
+                    fonts.push(name.to_string());
+                }
+            }
+        }
+        fonts
+    }
+}
+
+pub(crate) fn parse_type_tool(input: &[u8]) -> IResult<&[u8], TextLayer> {
+    let (mut input, _version) = be_u16(input)?;
+    let mut transform = [0.0; 6];
+    for value in &mut transform {
+        let (i, v) = be_f64(input)?;
+        *value = v;
+        input = i;
+    }
+    let (input, _text_version) = be_u16(input)?;
+    let (input, descriptor) = parse_versioned_descriptor(input)?;
+    let text = descriptor.get("Txt ").and_then(|text| text.as_str()).unwrap_or("").to_string();
+    // engine data that can't be parsed is left out, the text itself doesn't depend on it
+    let engine_data = descriptor.get("EngineData").and_then(|data| data.as_bytes()).and_then(|data| parse_engine_data(data).ok()).map(|(_, engine_data)| engine_data);
+    // the warp descriptor and the text bounds follow, neither is needed here
+    Ok((input, TextLayer { transform, text, engine_data }))
+}
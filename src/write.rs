@@ -0,0 +1,281 @@
+//! Serializing a parsed document back into the PSD format.
+//!
+//! Everything that isn't interpreted is copied as it was read: channel data stays compressed,
+//! and the extra data of the layer records and the image resources are written byte for byte.
+
+use std::convert::TryInto;
+use std::io::{self, Seek, SeekFrom, Write};
+
+use crate::header::{parse_length, Version};
+use crate::layer_info::{LayerRecord, LayerTreeNode};
+use crate::Psd;
+
+impl<'a> Psd<'a> {
+    /// Writes the document in the PSD format.
+    ///
+    /// For 16 and 32-bit documents the layers are written into an `Lr16`/`Lr32` block of the global additional layer information,
+    /// leaving the layer info section itself empty, as Photoshop does. Channel data is written as read, so it keeps its big-endian samples.
+    ///
+    /// The whole document is assembled in memory first, see [`write_streaming`](Self::write_streaming) for large documents.
+    pub fn write_to<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        let mut output = Vec::new();
+        write_psd(self, &mut output)?;
+        writer.write_all(&output)
+    }
+
+    /// Writes the same bytes as [`write_to`](Self::write_to), section by section, without buffering the document.
+    ///
+    /// The length of a section precedes its content, so each length is written as a placeholder and filled in by seeking back once the section is done.
+    /// This is why the writer has to implement [`Seek`]: writers that can't seek, such as pipes or sockets, aren't supported, use `write_to` for those.
+    /// Writing starts at the current position of `writer`.
+    pub fn write_streaming<W: Write + Seek>(&self, mut writer: W) -> io::Result<()> {
+        let position = writer.stream_position()?;
+        write_psd(self, &mut Streaming { writer, position })?;
+        Ok(())
+    }
+}
+
+/// Where a document is written to, [`write_block`] needs to go back and fill in lengths.
+trait Sink {
+    fn position(&self) -> u64;
+    fn put(&mut self, data: &[u8]) -> io::Result<()>;
+    fn patch(&mut self, position: u64, data: &[u8]) -> io::Result<()>;
+}
+
+impl Sink for Vec<u8> {
+    fn position(&self) -> u64 {
+        self.len() as u64
+    }
+    fn put(&mut self, data: &[u8]) -> io::Result<()> {
+        self.extend_from_slice(data);
+        Ok(())
+    }
+    fn patch(&mut self, position: u64, data: &[u8]) -> io::Result<()> {
+        self[position as usize..position as usize + data.len()].copy_from_slice(data);
+        Ok(())
+    }
+}
+
+struct Streaming<W> {
+    writer: W,
+    position: u64,
+}
+
+impl<W: Write + Seek> Sink for Streaming<W> {
+    fn position(&self) -> u64 {
+        self.position
+    }
+    fn put(&mut self, data: &[u8]) -> io::Result<()> {
+        self.writer.write_all(data)?;
+        self.position += data.len() as u64;
+        Ok(())
+    }
+    fn patch(&mut self, position: u64, data: &[u8]) -> io::Result<()> {
+        self.writer.seek(SeekFrom::Start(position))?;
+        self.writer.write_all(data)?;
+        self.writer.seek(SeekFrom::Start(self.position))?;
+        Ok(())
+    }
+}
+
+fn write_psd(psd: &Psd, output: &mut impl Sink) -> io::Result<()> {
+    write_header(psd, output)?;
+    write_block(output, 4, |output| output.put(psd.color_mode().data()))?;
+    write_block(output, 4, |output| write_image_resources(psd, output))?;
+    write_block(output, psd.header().version().length_size(), |output| write_layer_and_mask_information(psd, output))?;
+    output.put(&(psd.image_data().compression() as u16).to_be_bytes())?;
+    output.put(psd.image_data().compressed_data())
+}
+
+/// Writes a length prefix of `len_size` bytes followed by what `content` writes.
+fn write_block<S: Sink>(output: &mut S, len_size: usize, content: impl FnOnce(&mut S) -> io::Result<()>) -> io::Result<()> {
+    let start = output.position();
+    output.put(&[0; 8][..len_size])?;
+    content(output)?;
+    let len = output.position() - start - len_size as u64;
+    output.patch(start, &len.to_be_bytes()[8 - len_size..])
+}
+
+fn pad(output: &mut impl Sink, start: u64, multiple: u64) -> io::Result<()> {
+    let len = output.position() - start;
+    output.put(&[0; 8][..(len.next_multiple_of(multiple) - len) as usize])
+}
+
+fn write_header(psd: &Psd, output: &mut impl Sink) -> io::Result<()> {
+    let header = psd.header();
+    output.put(b"8BPS")?;
+    output.put(&(header.version() as u16).to_be_bytes())?;
+    output.put(&[0; 6])?;
+    output.put(&header.channels().to_be_bytes())?;
+    output.put(&header.height().to_be_bytes())?;
+    output.put(&header.width().to_be_bytes())?;
+    output.put(&header.depth().to_be_bytes())?;
+    output.put(&(header.color_mode() as u16).to_be_bytes())
+}
+
+fn write_image_resources(psd: &Psd, output: &mut impl Sink) -> io::Result<()> {
+    for block in psd.image_resources().data() {
+        output.put(b"8BIM")?;
+        output.put(&block.resource_id().to_be_bytes())?;
+        let start = output.position();
+        output.put(&[block.name().len() as u8])?;
+        output.put(block.name())?;
+        pad(output, start, 2)?;
+        let start = output.position() + 4;
+        write_block(output, 4, |output| output.put(block.resource_data()))?;
+        pad(output, start, 2)?;
+    }
+    Ok(())
+}
+
+fn write_layer_and_mask_information(psd: &Psd, output: &mut impl Sink) -> io::Result<()> {
+    let information = psd.layer_information();
+    let layers = information.layer_info();
+    let version = psd.header().version();
+    let deep_key: Option<&[u8; 4]> = match psd.header().depth() {
+        16 => Some(b"Lr16"),
+        32 => Some(b"Lr32"),
+        _ => None,
+    };
+    match deep_key {
+        Some(_) if !layers.is_empty() => output.put(&[0; 8][..version.length_size()])?,
+        _ => {
+            let start = output.position() + version.length_size() as u64;
+            write_block(output, version.length_size(), |output| write_layer_info(psd, output))?;
+            pad(output, start, 2)?;
+        }
+    }
+    write_block(output, 4, |output| output.put(information.global_layer_mask_info()))?;
+    match deep_key {
+        Some(key) if !layers.is_empty() => {
+            output.put(b"8BIM")?;
+            output.put(key)?;
+            let start = output.position() + version.block_length_size(key) as u64;
+            write_block(output, version.block_length_size(key), |output| {
+                write_layer_info(psd, output)?;
+                pad(output, start, 4)
+            })?;
+            // the layers written above replace any the source document had
+            for (signature, key, data) in global_blocks(information.additional_layer_information(), version) {
+                if !matches!(key, b"Layr" | b"Lr16" | b"Lr32") {
+                    output.put(signature)?;
+                    output.put(key)?;
+                    write_block(output, version.block_length_size(key), |output| output.put(data))?;
+                }
+            }
+            Ok(())
+        }
+        _ => output.put(information.additional_layer_information()),
+    }
+}
+
+/// Splits the global additional layer information into `(signature, key, data)` blocks, stopping at anything malformed.
+fn global_blocks(mut input: &[u8], version: Version) -> Vec<(&[u8; 4], &[u8; 4], &[u8])> {
+    let mut blocks = Vec::new();
+    while input.len() >= 12 {
+        let (signature, key): (&[u8; 4], &[u8; 4]) = (input[..4].try_into().unwrap(), input[4..8].try_into().unwrap());
+        let Ok((rest, len)) = parse_length(&input[8..], version.block_length_size(key)) else { break };
+        let Some(data) = rest.get(..len as usize) else { break };
+        blocks.push((signature, key, data));
+        input = &rest[len as usize..];
+    }
+    blocks
+}
+
+/// Writes the layer count, the layer records and the channel image data, bottom-most layer first as the file stores them.
+fn write_layer_info(psd: &Psd, output: &mut impl Sink) -> io::Result<()> {
+    fn collect<'t, 'a>(nodes: &'t [LayerTreeNode<'a>], records: &mut Vec<Record<'t, 'a>>) {
+        for node in nodes.iter().rev() {
+            match node {
+                LayerTreeNode::Leaf(record) => records.push(Record::Layer(record)),
+                LayerTreeNode::Node { folder, children } => {
+                    records.push(Record::Boundary(folder));
+                    collect(children, records);
+                    records.push(Record::Layer(folder));
+                }
+            }
+        }
+    }
+    let information = psd.layer_information();
+    let mut records = Vec::new();
+    collect(information.layer_info(), &mut records);
+    let count = records.len() as i16;
+    output.put(&if information.merged_transparency() { -count } else { count }.to_be_bytes())?;
+    for record in &records {
+        record.write_record(output, psd.header().version())?;
+    }
+    for record in &records {
+        record.write_channel_data(output)?;
+    }
+    Ok(())
+}
+
+enum Record<'t, 'a> {
+    Layer(&'t LayerRecord<'a>),
+    /// The hidden `</Layer group>` record closing the group of this folder, which the layer tree doesn't keep.
+    Boundary(&'t LayerRecord<'a>),
+}
+
+impl<'t, 'a> Record<'t, 'a> {
+    fn channels(&self) -> Vec<(i16, &'t [u8])> {
+        let (Record::Layer(record) | Record::Boundary(record)) = *self;
+        let channels = record
+            .transparency_mask()
+            .into_iter()
+            .chain(record.channel_info())
+            .chain(record.user_supplied_layer_mask())
+            .chain(record.real_user_supplied_layer_mask());
+        match self {
+            Record::Layer(_) => channels.map(|channel| (channel.channel_id(), channel.compressed_bytes())).collect(),
+            // an empty raw channel is only the compression method
+            Record::Boundary(_) => channels.filter(|channel| channel.channel_id() >= -1).map(|channel| (channel.channel_id(), &[0u8, 0][..])).collect(),
+        }
+    }
+
+    fn write_record(&self, output: &mut impl Sink, version: Version) -> io::Result<()> {
+        let rect = match *self {
+            Record::Layer(record) => [record.layer_top(), record.layer_left(), record.layer_bottom(), record.layer_right()],
+            Record::Boundary(_) => [0; 4],
+        };
+        for value in rect {
+            output.put(&value.to_be_bytes())?;
+        }
+        let channels = self.channels();
+        output.put(&(channels.len() as u16).to_be_bytes())?;
+        for (id, data) in &channels {
+            output.put(&id.to_be_bytes())?;
+            output.put(&(data.len() as u64).to_be_bytes()[8 - version.length_size()..])?;
+        }
+        output.put(b"8BIM")?;
+        match self {
+            Record::Layer(record) => {
+                output.put(record.blend_mode().key())?;
+                output.put(&[record.opacity(), record.clipping().to_u8(), record.flags().bits(), 0])?;
+                write_block(output, 4, |output| output.put(record.extra_data()))
+            }
+            Record::Boundary(record) => {
+                output.put(b"norm")?;
+                output.put(&[255, 0, record.flags().bits(), 0])?;
+                write_block(output, 4, |output| {
+                    // no mask data and no blending ranges
+                    output.put(&[0; 8])?;
+                    let start = output.position();
+                    let name = b"</Layer group>";
+                    output.put(&[name.len() as u8])?;
+                    output.put(name)?;
+                    pad(output, start, 4)?;
+                    output.put(b"8BIMlsct")?;
+                    output.put(&4u32.to_be_bytes())?;
+                    output.put(&3u32.to_be_bytes())
+                })
+            }
+        }
+    }
+
+    fn write_channel_data(&self, output: &mut impl Sink) -> io::Result<()> {
+        for (_, data) in self.channels() {
+            output.put(data)?;
+        }
+        Ok(())
+    }
+}
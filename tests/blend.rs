@@ -0,0 +1,78 @@
+use ya_psd::blend::blend;
+use ya_psd::layer_info::BlendMode;
+
+/// The backdrop of the separable modes, a gray ramp the source is blended over.
+const GRADIENT: [u8; 5] = [0, 64, 128, 192, 255];
+
+/// `mode` with the gray `source` over every gray of [`GRADIENT`], one channel of each result.
+fn over_gradient(mode: BlendMode, source: u8) -> Vec<u8> {
+    GRADIENT
+        .iter()
+        .map(|&backdrop| {
+            let [r, g, b] = blend(mode, [backdrop; 3], [source; 3]);
+            assert!(r == g && g == b, "{:?} of gray {} over gray {} isn't gray", mode, source, backdrop);
+            r
+        })
+        .collect()
+}
+
+#[test]
+fn vivid_light_burns_below_half_and_dodges_above() {
+    assert_eq!(over_gradient(BlendMode::Vividlight, 64), [0, 0, 2, 129, 255]);
+    assert_eq!(over_gradient(BlendMode::Vividlight, 192), [0, 130, 255, 255, 255]);
+}
+
+#[test]
+fn linear_light_adds_twice_the_source() {
+    assert_eq!(over_gradient(BlendMode::Linearlight, 64), [0, 0, 1, 65, 128]);
+    assert_eq!(over_gradient(BlendMode::Linearlight, 192), [129, 193, 255, 255, 255]);
+}
+
+#[test]
+fn pin_light_keeps_the_backdrop_between_its_limits() {
+    assert_eq!(over_gradient(BlendMode::Pinlight, 64), [0, 64, 128, 128, 128]);
+    assert_eq!(over_gradient(BlendMode::Pinlight, 192), [129, 129, 129, 192, 255]);
+}
+
+#[test]
+fn hard_mix_thresholds_the_sum() {
+    assert_eq!(over_gradient(BlendMode::Hardmix, 64), [0, 0, 0, 255, 255]);
+    assert_eq!(over_gradient(BlendMode::Hardmix, 192), [0, 255, 255, 255, 255]);
+    // pairs summing to exactly 255 are bright
+    assert_eq!(blend(BlendMode::Hardmix, [127; 3], [128; 3]), [255; 3]);
+    assert_eq!(blend(BlendMode::Hardmix, [126; 3], [128; 3]), [0; 3]);
+}
+
+#[test]
+fn luminosity_clips_towards_the_luminance() {
+    // white on red pushes every channel past 1 and lands on white
+    assert_eq!(blend(BlendMode::Luminosity, [255, 0, 0], [255, 255, 255]), [255, 255, 255]);
+    // mid gray on red overshoots red, which is pulled back to 1 with the others scaled around the luminance
+    assert_eq!(blend(BlendMode::Luminosity, [255, 0, 0], [128, 128, 128]), [255, 74, 74]);
+    // dark gray on yellow undershoots blue, which is pulled up to 0
+    assert_eq!(blend(BlendMode::Luminosity, [255, 255, 0], [20, 20, 20]), [22, 22, 0]);
+    assert_eq!(blend(BlendMode::Luminosity, [0, 0, 255], [0, 0, 0]), [0, 0, 0]);
+    assert_eq!(blend(BlendMode::Luminosity, [40, 200, 90], [100, 100, 100]), [0, 160, 50]);
+}
+
+#[test]
+fn hue_takes_the_hue_of_the_source() {
+    assert_eq!(blend(BlendMode::Hue, [0, 0, 200], [255, 0, 0]), [73, 0, 0]);
+    // a gray backdrop has no saturation to give the hue
+    assert_eq!(blend(BlendMode::Hue, [128, 128, 128], [255, 0, 0]), [128, 128, 128]);
+    assert_eq!(blend(BlendMode::Hue, [200, 100, 50], [0, 128, 255]), [64, 139, 214]);
+}
+
+#[test]
+fn saturation_takes_the_saturation_of_the_source() {
+    assert_eq!(blend(BlendMode::Saturation, [200, 0, 0], [128, 128, 128]), [60, 60, 60]);
+    assert_eq!(blend(BlendMode::Saturation, [200, 100, 50], [0, 255, 0]), [251, 84, 0]);
+    assert_eq!(blend(BlendMode::Saturation, [60, 120, 180], [90, 110, 120]), [96, 111, 126]);
+}
+
+#[test]
+fn color_keeps_the_luminance_of_the_backdrop() {
+    assert_eq!(blend(BlendMode::Color, [128, 128, 128], [255, 0, 0]), [255, 74, 74]);
+    assert_eq!(blend(BlendMode::Color, [30, 30, 30], [0, 255, 255]), [0, 43, 43]);
+    assert_eq!(blend(BlendMode::Color, [200, 100, 50], [0, 0, 255]), [108, 108, 255]);
+}
@@ -0,0 +1,178 @@
+//! Builds small documents for the tests that need bytes the fixtures don't have, such as broken or unusual sections.
+// each test crate only uses a part of it
+#![allow(dead_code)]
+
+use ya_psd::header::Version;
+
+/// The sections of a document, written out by [`to_bytes`](Self::to_bytes) with their lengths and the header.
+pub struct Document {
+    pub version: Version,
+    pub width: u32,
+    pub height: u32,
+    pub channels: u16,
+    pub depth: u16,
+    /// The value of the color mode in the header, 1 for grayscale and 3 for RGB.
+    pub color_mode: u16,
+    pub color_mode_data: Vec<u8>,
+    /// The resource blocks, see [`resource`].
+    pub image_resources: Vec<u8>,
+    /// The layers, see [`Layer`]. With neither layers nor global blocks the layer and mask information is empty.
+    pub layers: Vec<Layer>,
+    /// The global additional layer information after the global layer mask info, see [`block`].
+    pub global_info: Vec<u8>,
+    /// The compression method of the image data.
+    pub compression: u16,
+    /// The image data after its compression method.
+    pub image_data: Vec<u8>,
+}
+
+impl Document {
+    /// An 8-bit document without layers, its image data raw and black.
+    pub fn new(width: u32, height: u32, channels: u16, color_mode: u16) -> Self {
+        Document {
+            version: Version::Psd,
+            width,
+            height,
+            channels,
+            depth: 8,
+            color_mode,
+            color_mode_data: Vec::new(),
+            image_resources: Vec::new(),
+            layers: Vec::new(),
+            global_info: Vec::new(),
+            compression: 0,
+            image_data: vec![0; (width * height * channels as u32) as usize],
+        }
+    }
+
+    /// The document with `compression` and `data` as its image data.
+    pub fn image_data(mut self, compression: u16, data: &[u8]) -> Self {
+        self.compression = compression;
+        self.image_data = data.to_vec();
+        self
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let psb = self.version == Version::Psb;
+        let length = |psd: &mut Vec<u8>, len: usize| match psb {
+            true => psd.extend_from_slice(&(len as u64).to_be_bytes()),
+            false => psd.extend_from_slice(&(len as u32).to_be_bytes()),
+        };
+        let mut psd = b"8BPS".to_vec();
+        psd.extend_from_slice(&[0, if psb { 2 } else { 1 }, 0, 0, 0, 0, 0, 0]);
+        psd.extend_from_slice(&self.channels.to_be_bytes());
+        psd.extend_from_slice(&self.height.to_be_bytes());
+        psd.extend_from_slice(&self.width.to_be_bytes());
+        psd.extend_from_slice(&self.depth.to_be_bytes());
+        psd.extend_from_slice(&self.color_mode.to_be_bytes());
+        psd.extend_from_slice(&(self.color_mode_data.len() as u32).to_be_bytes());
+        psd.extend_from_slice(&self.color_mode_data);
+        psd.extend_from_slice(&(self.image_resources.len() as u32).to_be_bytes());
+        psd.extend_from_slice(&self.image_resources);
+        if self.layers.is_empty() && self.global_info.is_empty() {
+            length(&mut psd, 0);
+        } else {
+            let layer_info = if self.layers.is_empty() { Vec::new() } else { layer_info(&self.layers, psb) };
+            let mut section = Vec::new();
+            length(&mut section, layer_info.len());
+            section.extend_from_slice(&layer_info);
+            // an empty global layer mask info
+            section.extend_from_slice(&[0; 4]);
+            section.extend_from_slice(&self.global_info);
+            length(&mut psd, section.len());
+            psd.extend_from_slice(&section);
+        }
+        psd.extend_from_slice(&self.compression.to_be_bytes());
+        psd.extend_from_slice(&self.image_data);
+        psd
+    }
+}
+
+/// A layer of normal blend mode, full opacity, without a mask or blending ranges.
+pub struct Layer {
+    pub name: Vec<u8>,
+    /// Top, left, bottom and right.
+    pub rect: [i32; 4],
+    /// The id of each channel with its data, the compression method first.
+    pub channels: Vec<(i16, Vec<u8>)>,
+}
+
+impl Layer {
+    pub fn new(name: &str, rect: [i32; 4]) -> Self {
+        Layer { name: name.as_bytes().to_vec(), rect, channels: Vec::new() }
+    }
+
+    /// The layer with the channel `id` holding `data` compressed with `compression`.
+    pub fn channel(mut self, id: i16, compression: u16, data: &[u8]) -> Self {
+        let mut channel = compression.to_be_bytes().to_vec();
+        channel.extend_from_slice(data);
+        self.channels.push((id, channel));
+        self
+    }
+}
+
+/// The layer count, the records, then the channel data of every layer in order, padded to an even length.
+fn layer_info(layers: &[Layer], psb: bool) -> Vec<u8> {
+    let mut info = (layers.len() as i16).to_be_bytes().to_vec();
+    for layer in layers {
+        for edge in layer.rect {
+            info.extend_from_slice(&edge.to_be_bytes());
+        }
+        info.extend_from_slice(&(layer.channels.len() as u16).to_be_bytes());
+        for (id, data) in &layer.channels {
+            info.extend_from_slice(&id.to_be_bytes());
+            match psb {
+                true => info.extend_from_slice(&(data.len() as u64).to_be_bytes()),
+                false => info.extend_from_slice(&(data.len() as u32).to_be_bytes()),
+            }
+        }
+        info.extend_from_slice(b"8BIMnorm\xff\x00\x00\x00");
+        // the name is a pascal string padded to a multiple of 4 bytes
+        let mut name = vec![layer.name.len() as u8];
+        name.extend_from_slice(&layer.name);
+        name.resize(name.len().next_multiple_of(4), 0);
+        info.extend_from_slice(&(8 + name.len() as u32).to_be_bytes());
+        info.extend_from_slice(&[0; 8]);
+        info.extend_from_slice(&name);
+    }
+    for layer in layers {
+        layer.channels.iter().for_each(|(_, data)| info.extend_from_slice(data));
+    }
+    if info.len() % 2 == 1 {
+        info.push(0);
+    }
+    info
+}
+
+/// An additional layer information block with a 4-byte length.
+pub fn block(key: &[u8; 4], data: &[u8]) -> Vec<u8> {
+    let mut block = b"8BIM".to_vec();
+    block.extend_from_slice(key);
+    block.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    block.extend_from_slice(data);
+    block
+}
+
+/// An image resource block, the Pascal string of the name and the data padded to even lengths.
+pub fn resource(id: u16, name: &[u8], data: &[u8]) -> Vec<u8> {
+    let mut resource = b"8BIM".to_vec();
+    resource.extend_from_slice(&id.to_be_bytes());
+    resource.push(name.len() as u8);
+    resource.extend_from_slice(name);
+    if (1 + name.len()) % 2 == 1 {
+        resource.push(0);
+    }
+    resource.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    resource.extend_from_slice(data);
+    if data.len() % 2 == 1 {
+        resource.push(0);
+    }
+    resource
+}
+
+/// The 2-byte byte counts of RLE compressed rows followed by their packets.
+pub fn rle_rows(byte_counts: &[u16], packets: &[u8]) -> Vec<u8> {
+    let mut compressed: Vec<u8> = byte_counts.iter().flat_map(|count| count.to_be_bytes()).collect();
+    compressed.extend_from_slice(packets);
+    compressed
+}
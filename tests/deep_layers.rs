@@ -0,0 +1,45 @@
+use ya_psd::layer_info::{AdditionalLayerInformation, LayerTreeNode};
+use ya_psd::parse_psd;
+
+const LR16_LAYERS: &[u8] = include_bytes!("fixtures/lr16_layers.psd");
+
+#[test]
+fn layers_of_a_16_bit_document_come_from_lr16() {
+    let psd = parse_psd(LR16_LAYERS).unwrap();
+    assert_eq!(psd.header().depth(), 16);
+    let layers = psd.layer_information().layer_info();
+    assert_eq!(layers.len(), 2);
+    match &layers[0] {
+        LayerTreeNode::Node { folder, children } => {
+            assert_eq!(folder.layer_name(), b"Group");
+            assert_eq!(children.len(), 1);
+            let LayerTreeNode::Leaf(inner) = &children[0] else { panic!("a group inside the group") };
+            assert_eq!(inner.layer_name(), b"Inner");
+            assert_eq!(inner.channel_info()[0].raw_data(), [0x12, 0x34]);
+            assert_eq!(inner.transparency_mask().unwrap().raw_data(), [0x80, 0x00]);
+        }
+        LayerTreeNode::Leaf(layer) => panic!("{:?} instead of the group", String::from_utf8_lossy(layer.layer_name())),
+    }
+    match &layers[1] {
+        LayerTreeNode::Leaf(background) => {
+            assert_eq!(background.layer_name(), b"Background");
+            assert_eq!(background.channel_info()[0].raw_data(), [0xff, 0xff, 0xff, 0xff, 0, 0, 0, 0]);
+        }
+        LayerTreeNode::Node { .. } => panic!("a group instead of the background"),
+    }
+}
+
+#[test]
+fn lr16_block_is_not_kept_among_the_global_blocks() {
+    let psd = parse_psd(LR16_LAYERS).unwrap();
+    let deep_block = psd.layer_information().global_additional_info().iter().any(|info| matches!(info, AdditionalLayerInformation::Unknown { key, .. } if **key == *b"Lr16"));
+    assert!(!deep_block);
+}
+
+#[test]
+fn written_16_bit_document_reads_back_the_same() {
+    let psd = parse_psd(LR16_LAYERS).unwrap();
+    let mut written = Vec::new();
+    psd.write_to(&mut written).unwrap();
+    assert!(psd.structurally_eq(&parse_psd(&written).unwrap()));
+}
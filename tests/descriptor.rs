@@ -0,0 +1,83 @@
+mod common;
+
+use std::borrow::Cow;
+
+use ya_psd::descriptor::{Descriptor, DescriptorValue};
+use ya_psd::layer_info::AdditionalLayerInformation;
+use ya_psd::{parse_psd, Psd};
+
+use common::{block, Document};
+
+/// A 4-byte id, written with a length of 0.
+fn id(id: &[u8; 4]) -> Vec<u8> {
+    let mut data = vec![0; 4];
+    data.extend_from_slice(id);
+    data
+}
+
+/// A `vogk` block holding a `null` descriptor without a name whose items are `items`, each a key then an OSType and its value.
+fn vogk(items: &[(&[u8; 4], &[u8; 4], &[u8])]) -> Vec<u8> {
+    let mut data = [1u32, 16, 0].iter().flat_map(|value| value.to_be_bytes()).collect::<Vec<u8>>();
+    data.extend(id(b"null"));
+    data.extend_from_slice(&(items.len() as u32).to_be_bytes());
+    for (key, os_type, value) in items {
+        data.extend(id(key));
+        data.extend_from_slice(*os_type);
+        data.extend_from_slice(value);
+    }
+    block(b"vogk", &data)
+}
+
+/// A 1x1 grayscale document without layers whose global information holds `block`.
+fn with_global_block(block: Vec<u8>) -> Vec<u8> {
+    let mut document = Document::new(1, 1, 1, 1);
+    document.global_info = block;
+    document.to_bytes()
+}
+
+/// The descriptor of the `vogk` block of `psd`, or `None` when it couldn't be parsed and was kept unknown.
+fn vogk_descriptor<'p>(psd: &'p Psd) -> Option<&'p Descriptor<'p>> {
+    match &psd.layer_information().global_additional_info()[0] {
+        AdditionalLayerInformation::VectorOrigination(descriptor) => Some(descriptor),
+        AdditionalLayerInformation::Unknown { .. } => None,
+        info => panic!("{:?} instead of the vogk block", info),
+    }
+}
+
+#[test]
+fn unknown_os_types_are_kept_raw_and_skipped() {
+    let data = with_global_block(vogk(&[(b"Xtra", b"Zzzz", &[0, 0, 0, 3, 1, 2, 3]), (b"Cnt ", b"long", &[0, 0, 0, 7])]));
+    let psd = parse_psd(&data).unwrap();
+    let descriptor = vogk_descriptor(&psd).unwrap();
+    assert_eq!(descriptor.get("Xtra"), Some(&DescriptorValue::Raw { os_type: *b"Zzzz", data: Cow::Borrowed(&[1, 2, 3][..]) }));
+    assert_eq!(descriptor.get("Cnt ").and_then(DescriptorValue::as_i64), Some(7));
+}
+
+#[test]
+fn unknown_os_types_whose_length_runs_past_the_data_fail_the_descriptor() {
+    let data = with_global_block(vogk(&[(b"Xtra", b"Zzzz", &[0, 0, 1, 0, 1, 2, 3])]));
+    let psd = parse_psd(&data).unwrap();
+    assert!(vogk_descriptor(&psd).is_none());
+}
+
+/// A `vogk` block whose descriptor holds a descriptor in its `Objc` item `Chld`, and so on down to `depth` descriptors in all.
+fn nested_vogk(depth: usize) -> Vec<u8> {
+    // a descriptor ends with its last item, so the parents are only written before the innermost one
+    let parent = [vec![0; 4], id(b"null"), 1u32.to_be_bytes().to_vec(), id(b"Chld"), b"Objc".to_vec()].concat();
+    let descriptor = [parent.repeat(depth - 1), vec![0; 4], id(b"null"), vec![0; 4]].concat();
+    block(b"vogk", &[&1u32.to_be_bytes()[..], &16u32.to_be_bytes(), &descriptor].concat())
+}
+
+#[test]
+fn descriptors_nested_up_to_the_limit_parse() {
+    let data = with_global_block(nested_vogk(64));
+    assert!(vogk_descriptor(&parse_psd(&data).unwrap()).is_some());
+    let data = with_global_block(nested_vogk(65));
+    assert!(vogk_descriptor(&parse_psd(&data).unwrap()).is_none());
+}
+
+#[test]
+fn deeply_nested_descriptors_fail_instead_of_overflowing_the_stack() {
+    let data = with_global_block(nested_vogk(100_000));
+    assert!(vogk_descriptor(&parse_psd(&data).unwrap()).is_none());
+}
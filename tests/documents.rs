@@ -0,0 +1,213 @@
+use std::convert::TryInto;
+
+use ya_psd::header::ColorMode;
+use ya_psd::layer_info::{LayerColor, LayerTreeNode};
+use ya_psd::{parse_psd, Psd};
+
+// the fixtures are written by fixtures/generate.py
+
+/// 8x2 bitmap, the first row black, white, black then white, the second row white then black. No layers.
+const BITMAP: &[u8] = include_bytes!("fixtures/bitmap.psd");
+/// 4x2 grayscale, the first row a ramp from black to white and the second row white, with one RLE compressed layer `Gray` of the same pixels.
+const GRAYSCALE: &[u8] = include_bytes!("fixtures/grayscale.psd");
+/// 4x2 indexed, palette index 0 green and 1 red, the first row 0, 1, 2, 3 and the second row 1. No layers.
+const INDEXED: &[u8] = include_bytes!("fixtures/indexed.psd");
+/// 4x2 RGB with RLE compressed layers: a red `Background`, a pass-through `Group` holding a blue 2x1 `Inner` at the top left,
+/// and a green 2x1 `Top` at the bottom right, half transparent in its last pixel.
+const RGB_GROUPS: &[u8] = include_bytes!("fixtures/rgb_groups.psd");
+/// 4x2 RGB with a single raw blue layer `Masked`, whose user mask hides its left half.
+const RGB_MASK: &[u8] = include_bytes!("fixtures/rgb_mask.psd");
+/// 4x2 CMYK, the first row full cyan and the second row without ink, with one layer `Cyan` of the same pixels.
+const CMYK: &[u8] = include_bytes!("fixtures/cmyk.psd");
+/// 4x2 duotone with the pixels of `GRAYSCALE` and a monotone color mode data. No layers.
+const DUOTONE: &[u8] = include_bytes!("fixtures/duotone.psd");
+/// 4x2 Lab, the first row white and the second row black. No layers.
+const LAB: &[u8] = include_bytes!("fixtures/lab.psd");
+/// 4x2 multichannel with two channels. No layers.
+const MULTICHANNEL: &[u8] = include_bytes!("fixtures/multichannel.psd");
+/// 4x2 gray RGB with color labels: `Background` without one, a violet `Group` holding a hidden green `Hidden`, and a red `Say "hi"` on top.
+const LABELS: &[u8] = include_bytes!("fixtures/labels.psd");
+
+fn pixel(psd: &Psd, rgba: &[u8], x: u32, y: u32) -> [u8; 4] {
+    let index = (y * psd.header().width() + x) as usize * 4;
+    rgba[index..index + 4].try_into().unwrap()
+}
+
+fn layer_names(psd: &Psd) -> Vec<String> {
+    psd.layers_flat(true).into_iter().map(|layer| layer.unicode_name().into_owned()).collect()
+}
+
+#[test]
+fn every_fixture_decodes_to_its_header_size() {
+    let fixtures = [
+        (BITMAP, ColorMode::Bitmap, 8, 2, 1),
+        (GRAYSCALE, ColorMode::Grayscale, 4, 2, 1),
+        (INDEXED, ColorMode::Indexed, 4, 2, 1),
+        (RGB_GROUPS, ColorMode::RGB, 4, 2, 3),
+        (RGB_MASK, ColorMode::RGB, 4, 2, 3),
+        (CMYK, ColorMode::CMYK, 4, 2, 4),
+        (DUOTONE, ColorMode::Duotone, 4, 2, 1),
+        (LAB, ColorMode::Lab, 4, 2, 3),
+        (MULTICHANNEL, ColorMode::Multichannel, 4, 2, 2),
+        (LABELS, ColorMode::RGB, 4, 2, 3),
+    ];
+    for (data, color_mode, width, height, channels) in fixtures {
+        let psd = parse_psd(data).unwrap();
+        let header = psd.header();
+        assert_eq!((header.color_mode(), header.width(), header.height(), header.channels()), (color_mode, width, height, channels));
+        psd.validate_dimensions_against_data().unwrap();
+        let mut written = Vec::new();
+        psd.write_to(&mut written).unwrap();
+        assert!(parse_psd(&written).unwrap().structurally_eq(&psd), "{:?} doesn't read back the same", color_mode);
+    }
+}
+
+#[test]
+fn bitmap_pixels_are_black_where_set() {
+    let psd = parse_psd(BITMAP).unwrap();
+    let rgba = psd.to_rgba8().unwrap();
+    assert_eq!(pixel(&psd, &rgba, 0, 0), [0, 0, 0, 255]);
+    assert_eq!(pixel(&psd, &rgba, 1, 0), [255, 255, 255, 255]);
+    assert_eq!(pixel(&psd, &rgba, 7, 1), [0, 0, 0, 255]);
+}
+
+#[test]
+fn grayscale_layer_and_merged_image() {
+    let psd = parse_psd(GRAYSCALE).unwrap();
+    assert_eq!(layer_names(&psd), ["Gray"]);
+    let layer = psd.layer_information().find_layer("Gray").unwrap();
+    assert_eq!(layer.channel_info()[0].raw_data(), [0, 85, 170, 255, 255, 255, 255, 255]);
+    let rgba = psd.to_rgba8().unwrap();
+    assert_eq!(pixel(&psd, &rgba, 1, 0), [85, 85, 85, 255]);
+}
+
+#[test]
+fn indexed_pixels_go_through_the_palette() {
+    let psd = parse_psd(INDEXED).unwrap();
+    assert!(layer_names(&psd).is_empty());
+    let palette = psd.color_mode().palette().unwrap();
+    assert_eq!(palette[0], [0, 255, 0]);
+    assert_eq!(palette[1], [255, 0, 0]);
+    let rgba = psd.to_rgba8().unwrap();
+    assert_eq!(pixel(&psd, &rgba, 0, 0), [0, 255, 0, 255]);
+    assert_eq!(pixel(&psd, &rgba, 3, 1), [255, 0, 0, 255]);
+}
+
+#[test]
+fn rgb_groups_nest_and_composite() {
+    let psd = parse_psd(RGB_GROUPS).unwrap();
+    assert_eq!(layer_names(&psd), ["Top", "Group", "Inner", "Background"]);
+    let layers = psd.layer_information().layer_info();
+    assert_eq!(layers.len(), 3);
+    match &layers[1] {
+        LayerTreeNode::Node { folder, children } => {
+            assert_eq!(folder.layer_name(), b"Group");
+            assert_eq!(children.len(), 1);
+        }
+        LayerTreeNode::Leaf(layer) => panic!("{:?} instead of the group", layer.unicode_name()),
+    }
+    let depths: Vec<_> = psd.layer_information().iter_layers().map(|(_, depth)| depth).collect();
+    assert_eq!(depths, [0, 0, 1, 0]);
+    let composite = psd.composite().unwrap();
+    assert_eq!(composite.pixel(0, 0), [0, 0, 255, 255]);
+    assert_eq!(composite.pixel(2, 0), [255, 0, 0, 255]);
+    assert_eq!(composite.pixel(2, 1), [0, 255, 0, 255]);
+    assert_eq!(composite.pixel(3, 1), [127, 128, 0, 255]);
+    assert!(psd.composite_matches_stored(1).unwrap());
+}
+
+#[test]
+fn rgb_user_mask_hides_the_layer() {
+    let psd = parse_psd(RGB_MASK).unwrap();
+    let layer = psd.layer_information().find_layer("Masked").unwrap();
+    let mask = layer.layer_mask_data().unwrap();
+    assert!(!mask.disabled());
+    assert!(!mask.inverted());
+    assert_eq!(layer.user_supplied_layer_mask().unwrap().raw_data(), [0, 0, 255, 255, 0, 0, 255, 255]);
+    let composite = psd.composite().unwrap();
+    assert_eq!(composite.pixel(0, 0), [0, 0, 0, 0]);
+    assert_eq!(composite.pixel(3, 1), [0, 0, 255, 255]);
+}
+
+#[test]
+fn cmyk_is_stored_inverted() {
+    let psd = parse_psd(CMYK).unwrap();
+    assert_eq!(layer_names(&psd), ["Cyan"]);
+    let rgba = psd.to_rgba8().unwrap();
+    assert_eq!(pixel(&psd, &rgba, 0, 1), [255, 255, 255, 255]);
+    let [r, g, b, _] = pixel(&psd, &rgba, 0, 0);
+    assert!(r < 64 && g > 128 && b > 128, "cyan came out as {:?}", [r, g, b]);
+}
+
+#[test]
+fn duotone_renders_its_gray_channel() {
+    let psd = parse_psd(DUOTONE).unwrap();
+    assert_eq!(psd.color_mode().duotone().unwrap().ink_count(), 1);
+    let rgba = psd.to_rgba8().unwrap();
+    assert_eq!(pixel(&psd, &rgba, 2, 0), [170, 170, 170, 255]);
+}
+
+#[test]
+fn lab_lightness_goes_from_white_to_black() {
+    let psd = parse_psd(LAB).unwrap();
+    assert_eq!(psd.lab_pixels().unwrap()[0], [100.0, 0.0, 0.0]);
+    let rgba = psd.to_rgba8().unwrap();
+    let [r, g, b, _] = pixel(&psd, &rgba, 0, 0);
+    assert!(r > 250 && g > 250 && b > 250, "white came out as {:?}", [r, g, b]);
+    let [r, g, b, _] = pixel(&psd, &rgba, 0, 1);
+    assert!(r < 5 && g < 5 && b < 5, "black came out as {:?}", [r, g, b]);
+}
+
+#[test]
+fn multichannel_keeps_its_channels() {
+    let psd = parse_psd(MULTICHANNEL).unwrap();
+    let channels = psd.image_data().raw_data();
+    assert_eq!(channels.len(), 2);
+    assert_eq!(channels[0][..4], [0, 85, 170, 255]);
+    assert!(psd.to_rgba8().is_err());
+}
+
+#[test]
+fn only_indexed_documents_have_a_palette() {
+    assert!(parse_psd(INDEXED).unwrap().color_mode().palette().is_some());
+    // 768 bytes of color mode data that aren't a palette
+    let mut duotone = DUOTONE.to_vec();
+    let data_len = u32::from_be_bytes(duotone[26..30].try_into().unwrap()) as usize;
+    duotone.splice(26..30 + data_len, [&768u32.to_be_bytes()[..], &[0; 768]].concat());
+    let psd = parse_psd(&duotone).unwrap();
+    assert_eq!(psd.color_mode().data().len(), 768);
+    assert!(psd.color_mode().palette().is_none());
+}
+
+#[test]
+fn additional_info_bytes_hold_exactly_the_blocks() {
+    let psd = parse_psd(RGB_GROUPS).unwrap();
+    for (layer, _) in psd.layer_information().iter_layers() {
+        let mut bytes = &layer.additional_info_bytes()[..];
+        let mut blocks = 0;
+        while !bytes.is_empty() {
+            assert_eq!(&bytes[..4], b"8BIM", "{:?}", layer.unicode_name());
+            blocks += 1;
+            let len = u32::from_be_bytes(bytes[8..12].try_into().unwrap()) as usize;
+            bytes = &bytes[12 + len..];
+        }
+        assert_eq!(blocks, layer.additional_layer_info().len(), "{:?}", layer.unicode_name());
+    }
+}
+
+#[test]
+fn layer_panel_json_has_the_labels_and_visibility_of_every_layer() {
+    let psd = parse_psd(LABELS).unwrap();
+    let hidden = psd.layer_information().find_layer("Hidden").unwrap();
+    assert!(!hidden.is_visible());
+    assert_eq!(hidden.color_label(), Some(LayerColor::Green));
+    assert_eq!(
+        psd.layer_panel_json(),
+        concat!(
+            r#"[{"name":"Say \"hi\"","depth":0,"group":false,"color_label":"red","visible":true},"#,
+            r#"{"name":"Group","depth":0,"group":true,"color_label":"violet","visible":true},"#,
+            r#"{"name":"Hidden","depth":1,"group":false,"color_label":"green","visible":false},"#,
+            r#"{"name":"Background","depth":0,"group":false,"color_label":null,"visible":true}]"#,
+        )
+    );
+}
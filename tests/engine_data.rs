@@ -0,0 +1,35 @@
+mod common;
+
+use ya_psd::layer_info::AdditionalLayerInformation;
+use ya_psd::parse_psd;
+
+use common::{block, Document};
+
+/// Whether a document whose global information holds `engine_data` in a `Txt2` block parses it.
+fn parses(engine_data: &[u8]) -> bool {
+    let mut document = Document::new(1, 1, 1, 1);
+    document.global_info = block(b"Txt2", engine_data);
+    let data = document.to_bytes();
+    let psd = parse_psd(&data).unwrap();
+    match &psd.layer_information().global_additional_info()[0] {
+        AdditionalLayerInformation::TextEngineData(_) => true,
+        AdditionalLayerInformation::Unknown { .. } => false,
+        info => panic!("{:?} instead of the Txt2 block", info),
+    }
+}
+
+fn nested_arrays(depth: usize) -> Vec<u8> {
+    [vec![b'['; depth], vec![b']'; depth]].concat()
+}
+
+#[test]
+fn arrays_nested_up_to_the_limit_parse() {
+    assert!(parses(&nested_arrays(64)));
+    assert!(!parses(&nested_arrays(65)));
+}
+
+#[test]
+fn deeply_nested_dictionaries_fail_instead_of_overflowing_the_stack() {
+    let engine_data = [b"<< /A ".repeat(100_000), b">> ".repeat(100_000)].concat();
+    assert!(!parses(&engine_data));
+}
@@ -0,0 +1,33 @@
+use ya_psd::fixed::{fixed_16_16, fixed_27_5, fixed_8_24, fixed_8_8};
+
+#[test]
+fn fixed_16_16_round_trips() {
+    for (raw, value) in [(0, 0.0), (72 << 16, 72.0), (0x012c_8000, 300.5), (1, 1.0 / 65536.0), (u32::MAX, 65535.0 + 65535.0 / 65536.0)] {
+        assert_eq!(fixed_16_16(raw), value);
+        assert_eq!((fixed_16_16(raw) * 65536.0) as u32, raw);
+    }
+}
+
+#[test]
+fn fixed_8_8_round_trips() {
+    for (raw, value) in [(0, 0.0), (1 << 8, 1.0), (0x0180, 1.5), (1, 1.0 / 256.0), (u16::MAX, 255.0 + 255.0 / 256.0)] {
+        assert_eq!(fixed_8_8(raw), value);
+        assert_eq!((fixed_8_8(raw) * 256.0) as u16, raw);
+    }
+}
+
+#[test]
+fn fixed_8_24_round_trips_signed_values() {
+    for (raw, value) in [(0, 0.0), (1 << 24, 1.0), (0x0080_0000, 0.5), (-(1 << 24), -1.0), (-0x0040_0000, -0.25), (i32::MIN, -128.0)] {
+        assert_eq!(fixed_8_24(raw), value);
+        assert_eq!((fixed_8_24(raw) * (1 << 24) as f64) as i32, raw);
+    }
+}
+
+#[test]
+fn fixed_27_5_round_trips_signed_values() {
+    for (raw, value) in [(0, 0.0), (32, 1.0), (100 * 32 + 16, 100.5), (-48, -1.5), (i32::MAX, i32::MAX as f64 / 32.0)] {
+        assert_eq!(fixed_27_5(raw), value);
+        assert_eq!((fixed_27_5(raw) * 32.0) as i32, raw);
+    }
+}
@@ -0,0 +1,231 @@
+#!/usr/bin/env python3
+"""Writes the fixtures of tests/documents.rs next to this script.
+
+Run it from anywhere with `python3 tests/fixtures/generate.py`, or pass another output directory.
+Every file is a tiny version 1 PSD, the channels RLE compressed unless noted.
+"""
+import os
+import struct
+import sys
+
+
+def u8(v): return struct.pack('>B', v)
+def u16(v): return struct.pack('>H', v)
+def i16(v): return struct.pack('>h', v)
+def u32(v): return struct.pack('>I', v)
+def i32(v): return struct.pack('>i', v)
+
+
+def packbits_row(row):
+    """PackBits: runs of 2 or more as -(n - 1) followed by the byte, anything else as n - 1 followed by n literal bytes."""
+    out = bytearray()
+    i = 0
+    n = len(row)
+    while i < n:
+        j = i
+        while j + 1 < n and row[j + 1] == row[i] and j - i < 127:
+            j += 1
+        if j > i:
+            out += struct.pack('>b', -(j - i))
+            out.append(row[i])
+            i = j + 1
+        else:
+            start = i
+            while i < n and (i + 1 >= n or row[i + 1] != row[i]) and i - start < 128:
+                i += 1
+            if i == start:
+                i += 1
+            out.append(i - start - 1)
+            out += bytes(row[start:i])
+    return bytes(out)
+
+
+def rle(data, width, height):
+    """The 2-byte byte count of every row followed by the PackBits compressed rows."""
+    rows = [data[y * width:(y + 1) * width] for y in range(height)]
+    encoded = [packbits_row(row) for row in rows]
+    return b''.join(u16(len(row)) for row in encoded) + b''.join(encoded)
+
+
+def pascal(name, pad):
+    b = u8(len(name)) + name
+    while len(b) % pad:
+        b += b'\0'
+    return b
+
+
+def unicode(s):
+    units = s.encode('utf-16-be')
+    return u32(len(units) // 2) + units
+
+
+def ali(key, data):
+    """An additional layer information block."""
+    return b'8BIM' + key + u32(len(data)) + data
+
+
+def lsct(section_type, blend=None):
+    data = u32(section_type)
+    if blend:
+        data += b'8BIM' + blend
+    return ali(b'lsct', data)
+
+
+class Layer:
+    """A layer record, `rect` being (top, left, bottom, right) and `channels` (id, plane) pairs.
+    `mask` is ((top, left, bottom, right), default color, flags) for a user mask, whose plane is the channel -2."""
+
+    def __init__(self, name, rect, channels, blend=b'norm', extra=(), mask=None, compression=1, hidden=False):
+        self.name = name
+        self.hidden = hidden
+        self.rect = rect
+        self.channels = channels
+        self.blend = blend
+        self.extra = list(extra)
+        self.mask = mask
+        self.compression = compression
+
+
+def channel_bytes(layer, cid, data):
+    top, left, bottom, right = layer.rect
+    width, height = right - left, bottom - top
+    if cid == -2 and layer.mask:
+        top, left, bottom, right = layer.mask[0]
+        width, height = right - left, bottom - top
+    if layer.compression == 0 or width * height == 0:
+        return u16(0) + data
+    return u16(1) + rle(data, width, height)
+
+
+def layer_record(layer, channel_data):
+    top, left, bottom, right = layer.rect
+    record = i32(top) + i32(left) + i32(bottom) + i32(right) + u16(len(layer.channels))
+    for cid, _ in layer.channels:
+        record += i16(cid) + u32(len(channel_data[cid]))
+    # opacity 255, base clipping, and the flag called visible set for hidden layers
+    record += b'8BIM' + layer.blend + u8(255) + u8(0) + u8(2 if layer.hidden else 0) + b'\0'
+    if layer.mask:
+        (t, l, b, r), color, flags = layer.mask
+        mask = i32(t) + i32(l) + i32(b) + i32(r) + u8(color) + u8(flags) + b'\0\0'
+    else:
+        mask = b''
+    # no blending ranges
+    extra = u32(len(mask)) + mask + u32(0) + pascal(layer.name, 4) + b''.join(layer.extra)
+    return record + u32(len(extra)) + extra
+
+
+def layer_info(layers):
+    """The layer count, the records, then the channel data of every layer in order, padded to an even length."""
+    records = b''
+    data = b''
+    for layer in layers:
+        channel_data = {cid: channel_bytes(layer, cid, plane) for cid, plane in layer.channels}
+        records += layer_record(layer, channel_data)
+        for cid, _ in layer.channels:
+            data += channel_data[cid]
+    body = i16(len(layers)) + records + data
+    if len(body) % 2:
+        body += b'\0'
+    return body
+
+
+def psd(width, height, channels, depth, mode, planes, layers=None, color_data=b'', compression=1):
+    """A document of `planes` as its image data, without image resources, and with `layers` bottom-most first."""
+    out = b'8BPS' + u16(1) + b'\0' * 6 + u16(channels) + u32(height) + u32(width) + u16(depth) + u16(mode)
+    out += u32(len(color_data)) + color_data
+    out += u32(0)
+    if layers is None:
+        out += u32(0)
+    else:
+        info = layer_info(layers)
+        # followed by an empty global layer mask info
+        section = u32(len(info)) + info + u32(0)
+        out += u32(len(section)) + section
+    row_bytes = (width * depth + 7) // 8
+    if compression == 1:
+        tables = b''
+        encoded = b''
+        for plane in planes:
+            rows = [packbits_row(plane[y * row_bytes:(y + 1) * row_bytes]) for y in range(height)]
+            tables += b''.join(u16(len(row)) for row in rows)
+            encoded += b''.join(rows)
+        out += u16(1) + tables + encoded
+    else:
+        out += u16(0) + b''.join(planes)
+    return out
+
+
+def main(out):
+    W, H = 4, 2
+    black = bytes(8)
+    white = bytes([255] * 8)
+    fixtures = {}
+
+    # bitmap: 8x2 at 1 bit, a set bit is black, raw
+    fixtures['bitmap'] = psd(8, 2, 1, 1, 0, [bytes([0b10100000, 0b00001111])], compression=0)
+
+    # grayscale: a ramp over a white row, with the layer `Gray` of the same pixels and its transparency
+    gray = bytes([0, 85, 170, 255, 255, 255, 255, 255])
+    gray_layer = Layer(b'Gray', (0, 0, 2, 4), [(-1, white), (0, gray)], extra=[ali(b'luni', unicode('Gray')), ali(b'lyid', u32(2))])
+    fixtures['grayscale'] = psd(W, H, 1, 8, 1, [gray], layers=[gray_layer])
+
+    # indexed: a planar palette, index 0 green, 1 red and i otherwise (i, 255 - i, 0)
+    reds = b'\x00\xff' + bytes(range(2, 256))
+    greens = b'\xff\x00' + bytes(255 - i for i in range(2, 256))
+    blues = bytes(256)
+    fixtures['indexed'] = psd(W, H, 1, 8, 2, [bytes([0, 1, 2, 3, 1, 1, 1, 1])], color_data=reds + greens + blues)
+
+    # rgb with groups: a red background, a pass-through group holding a blue 2x1 layer at the top left,
+    # and a green 2x1 layer at the bottom right, half transparent in its last pixel
+    background = Layer(b'Background', (0, 0, 2, 4), [(0, white), (1, black), (2, black)])
+    divider = Layer(b'</Layer group>', (0, 0, 0, 0), [(-1, b''), (0, b''), (1, b''), (2, b'')], extra=[lsct(3)])
+    inner = Layer(b'Inner', (0, 0, 1, 2), [(-1, bytes([255, 255])), (0, bytes(2)), (1, bytes(2)), (2, bytes([255, 255]))])
+    group = Layer(b'Group', (0, 0, 0, 0), [(-1, b''), (0, b''), (1, b''), (2, b'')], extra=[lsct(1, b'pass')], blend=b'pass')
+    top = Layer(b'Top', (1, 2, 2, 4), [(-1, bytes([255, 128])), (0, bytes(2)), (1, bytes([255, 255])), (2, bytes(2))])
+    # the merged image matted against white
+    merged = [bytes([0, 0, 255, 255, 255, 255, 0, 127]), bytes([0, 0, 0, 0, 0, 0, 255, 128]), bytes([255, 255, 0, 0, 0, 0, 0, 0])]
+    fixtures['rgb_groups'] = psd(W, H, 3, 8, 3, merged, layers=[background, divider, inner, group, top])
+
+    # rgb with a blue layer whose user mask hides its left half, raw
+    left_hidden = bytes([0, 0, 255, 255, 0, 0, 255, 255])
+    masked = Layer(b'Masked', (0, 0, 2, 4), [(-1, white), (0, black), (1, black), (2, white), (-2, left_hidden)], mask=((0, 0, 2, 4), 0, 0), compression=0)
+    fixtures['rgb_mask'] = psd(W, H, 3, 8, 3, [black, black, left_hidden], layers=[masked], compression=0)
+
+    # cmyk, stored inverted so 255 is no ink: full cyan over no ink, with the layer `Cyan` of the same pixels
+    cyan = bytes([0] * 4 + [255] * 4)
+    fixtures['cmyk'] = psd(W, H, 4, 8, 4, [cyan, white, white, white], layers=[Layer(b'Cyan', (0, 0, 2, 4), [(0, cyan), (1, white), (2, white), (3, white)])])
+
+    # duotone: the grayscale pixels, the color mode data starting with version 1 and a single ink
+    fixtures['duotone'] = psd(W, H, 1, 8, 8, [gray], color_data=u16(1) + u16(1) + bytes(40))
+
+    # lab: lightness white over black, a and b neutral
+    fixtures['lab'] = psd(W, H, 3, 8, 9, [bytes([255] * 4 + [0] * 4), bytes([128] * 8), bytes([128] * 8)])
+
+    # multichannel: two channels
+    fixtures['multichannel'] = psd(W, H, 2, 8, 7, [gray, black])
+
+    # rgb with color labels: a gray `Background` without a label, a violet `Group` holding a hidden green `Hidden`,
+    # and a red `Say "hi"` whose name needs escaping in JSON
+    def lclr(color):
+        return ali(b'lclr', u16(color) + bytes(6))
+
+    def empty(name, **options):
+        return Layer(name, (0, 0, 0, 0), [(-1, b''), (0, b''), (1, b''), (2, b'')], **options)
+
+    gray_background = Layer(b'Background', (0, 0, 2, 4), [(0, bytes([128] * 8)), (1, bytes([128] * 8)), (2, bytes([128] * 8))])
+    labels = [
+        gray_background,
+        empty(b'</Layer group>', extra=[lsct(3)]),
+        empty(b'Hidden', extra=[lclr(4)], hidden=True),
+        empty(b'Group', extra=[lsct(1, b'pass'), lclr(6)], blend=b'pass'),
+        empty(b'Say "hi"', extra=[lclr(1)]),
+    ]
+    fixtures['labels'] = psd(W, H, 3, 8, 3, [bytes([128] * 8)] * 3, layers=labels)
+
+    for name, data in fixtures.items():
+        with open(os.path.join(out, name + '.psd'), 'wb') as file:
+            file.write(data)
+
+
+if __name__ == '__main__':
+    main(sys.argv[1] if len(sys.argv) > 1 else os.path.dirname(os.path.abspath(__file__)))
@@ -0,0 +1,58 @@
+mod common;
+
+use ya_psd::parse_psd;
+
+use common::{resource, Document};
+
+/// `(id, name, data)` of resources covering each combination of name and data padding.
+const RESOURCES: &[(u16, &[u8], &[u8])] = &[
+    (1005, b"", &[0, 72, 0, 0, 0, 1, 0, 1, 0, 72, 0, 0, 0, 1, 0, 1]),
+    (1008, b"a", b"\x05hello"),
+    (1034, b"ab", &[1]),
+    (1060, b"abc", b"<x:xmpmeta/>\n"),
+    (1044, b"", &[]),
+    (2999, b"unknown", &[0xde, 0xad, 0xbe]),
+    (4000, b"plug-in", &[0, 1, 2, 3, 4, 5]),
+    (0x7fff, b"\xff", &[0xff]),
+];
+
+/// A 1x1 grayscale document whose image resources section holds `RESOURCES`, returned with where that section starts and ends.
+fn document() -> (Vec<u8>, std::ops::Range<usize>) {
+    let mut document = Document::new(1, 1, 1, 1).image_data(0, &[0x80]);
+    document.image_resources = RESOURCES.iter().flat_map(|(id, name, data)| resource(*id, name, data)).collect();
+    // after the header and the lengths of the empty color mode data and of the section
+    let start = 26 + 4 + 4;
+    let end = start + document.image_resources.len();
+    (document.to_bytes(), start..end)
+}
+
+#[test]
+fn image_resources_are_read_with_their_padding_skipped() {
+    let (data, _) = document();
+    let psd = parse_psd(&data).unwrap();
+    let blocks = psd.image_resources().data();
+    assert_eq!(blocks.len(), RESOURCES.len());
+    for (block, (id, name, data)) in blocks.iter().zip(RESOURCES) {
+        assert_eq!(block.resource_id(), *id);
+        assert_eq!(block.name(), *name);
+        assert_eq!(block.resource_data(), *data);
+    }
+}
+
+#[test]
+fn written_image_resources_read_back_the_same() {
+    let (data, _) = document();
+    let psd = parse_psd(&data).unwrap();
+    let mut written = Vec::new();
+    psd.write_to(&mut written).unwrap();
+    assert_eq!(parse_psd(&written).unwrap().image_resources(), psd.image_resources());
+}
+
+#[test]
+fn image_resources_are_written_byte_for_byte() {
+    let (data, section) = document();
+    let mut written = Vec::new();
+    parse_psd(&data).unwrap().write_to(&mut written).unwrap();
+    assert_eq!(written[section.start - 4..section.start], data[section.start - 4..section.start]);
+    assert_eq!(written[section.clone()], data[section]);
+}
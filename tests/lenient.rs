@@ -0,0 +1,53 @@
+mod common;
+
+use ya_psd::layer_info::AdditionalLayerInformation;
+use ya_psd::{parse_psd_with_options, ParseOptions};
+
+use common::{block, Document};
+
+/// A 1x1 white grayscale document without layers whose layer and mask information holds `global` after the empty layer info and global mask.
+fn with_global_info(global: &[u8]) -> Vec<u8> {
+    let mut document = Document::new(1, 1, 1, 1).image_data(0, &[255]);
+    document.global_info = global.to_vec();
+    document.to_bytes()
+}
+
+fn keys(blocks: &[AdditionalLayerInformation]) -> Vec<[u8; 4]> {
+    blocks
+        .iter()
+        .map(|info| match info {
+            AdditionalLayerInformation::Unknown { key, .. } => **key,
+            info => panic!("{:?} instead of an unknown block", info),
+        })
+        .collect()
+}
+
+#[test]
+fn garbage_between_global_blocks_is_skipped_unless_strict() {
+    let mut global = block(b"abcd", &[1, 2, 3, 4]);
+    // reads as a block whose length runs past the end of the section
+    global.extend_from_slice(&[0xff; 16]);
+    global.extend_from_slice(&block(b"efgh", &[5, 6]));
+    let data = with_global_info(&global);
+
+    let lenient = parse_psd_with_options(&data, &ParseOptions { strict_signatures: false, ..ParseOptions::default() }).unwrap();
+    let blocks = lenient.layer_information().global_additional_info();
+    assert_eq!(keys(blocks), [*b"abcd", *b"efgh"]);
+    match &blocks[1] {
+        AdditionalLayerInformation::Unknown { data, .. } => assert_eq!(&data[..], [5, 6]),
+        info => panic!("{:?} instead of the unknown block", info),
+    }
+
+    let strict = parse_psd_with_options(&data, &ParseOptions::default()).unwrap();
+    assert_eq!(keys(strict.layer_information().global_additional_info()), [*b"abcd"]);
+}
+
+#[test]
+fn garbage_holding_a_partial_signature_resumes_at_the_whole_one() {
+    let mut global = block(b"abcd", &[]);
+    global.extend_from_slice(b"\x00\x008B8BI\xff\xff\xff\xff\xff\xff\xff\xff\xff");
+    global.extend_from_slice(&block(b"efgh", &[]));
+    let data = with_global_info(&global);
+    let psd = parse_psd_with_options(&data, &ParseOptions { strict_signatures: false, ..ParseOptions::default() }).unwrap();
+    assert_eq!(keys(psd.layer_information().global_additional_info()), [*b"abcd", *b"efgh"]);
+}
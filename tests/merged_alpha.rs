@@ -0,0 +1,51 @@
+mod common;
+
+use ya_psd::error::PsdError;
+use ya_psd::layer_info::ImageCompression;
+use ya_psd::parse_psd;
+
+use common::{block, Document};
+
+/// A 2x1 grayscale document without layers, with raw white image data and an `Alph` block holding `alpha`, a compression method then its data.
+fn with_merged_alpha(alpha: &[u8]) -> Vec<u8> {
+    let mut document = Document::new(2, 1, 1, 1).image_data(0, &[255, 255]);
+    document.global_info = block(b"Alph", alpha);
+    document.to_bytes()
+}
+
+#[test]
+fn raw_merged_alpha_becomes_the_alpha_of_the_image() {
+    let data = with_merged_alpha(&[0, 0, 0, 128]);
+    let psd = parse_psd(&data).unwrap();
+    assert_eq!(psd.layer_information().try_merged_alpha().unwrap(), Some(&[0, 128][..]));
+    assert_eq!(psd.to_rgba8().unwrap(), [255, 255, 255, 0, 255, 255, 255, 128]);
+}
+
+#[test]
+fn zip_merged_alpha_that_cant_be_decompressed_fails() {
+    let data = with_merged_alpha(&[0, 2, 1, 2, 3, 4]);
+    let psd = parse_psd(&data).unwrap();
+    let error = psd.to_rgba8().unwrap_err();
+    if cfg!(feature = "zip") {
+        assert!(matches!(error, PsdError::InvalidZipData(_)), "{:?}", error);
+        assert!(std::error::Error::source(&error).is_some());
+    } else {
+        assert!(matches!(error, PsdError::UnsupportedCompression(ImageCompression::ZipWithoutPrediction)), "{:?}", error);
+    }
+    assert!(psd.layer_information().try_merged_alpha().is_err());
+}
+
+#[cfg(feature = "zip")]
+#[test]
+fn zip_merged_alpha_inflating_past_the_image_fails_without_inflating_all_of_it() {
+    use std::io::Write;
+
+    let mut encoder = flate2::write::ZlibEncoder::new(Vec::new(), flate2::Compression::best());
+    encoder.write_all(&vec![0; 1 << 20]).unwrap();
+    let mut alpha = vec![0, 2];
+    alpha.extend(encoder.finish().unwrap());
+    let data = with_merged_alpha(&alpha);
+    let psd = parse_psd(&data).unwrap();
+    let error = psd.to_rgba8().unwrap_err();
+    assert!(matches!(error, PsdError::ZipSizeMismatch { expected: 2, actual: 3 }), "{:?}", error);
+}
@@ -0,0 +1,111 @@
+mod common;
+
+use ya_psd::error::PsdError;
+use ya_psd::header::Version;
+use ya_psd::layer_info::encode_rle;
+use ya_psd::parse_psd;
+
+use common::{rle_rows, Document, Layer};
+
+/// An 8-bit RGB document of `width` by `height` pixels without layers, whose image data holds `compressed`, RLE compressed byte counts then packets.
+fn rle_rgb_document(version: Version, width: u32, height: u32, compressed: &[u8]) -> Vec<u8> {
+    Document { version, ..Document::new(width, height, 3, 3) }.image_data(1, compressed).to_bytes()
+}
+
+/// [`rle_rgb_document`] from the 2-byte byte counts and the packets of a PSD.
+fn rle_rgb(width: u32, height: u32, byte_counts: &[u16], packets: &[u8]) -> Vec<u8> {
+    rle_rgb_document(Version::Psd, width, height, &rle_rows(byte_counts, packets))
+}
+
+/// An 8-bit grayscale document of `width` by `height` pixels with one layer `L` covering it, whose gray channel holds `compressed`,
+/// RLE compressed as byte counts then packets. The image data is raw and black.
+fn rle_gray_layer(width: u32, height: u32, compressed: &[u8]) -> Vec<u8> {
+    let mut document = Document::new(width, height, 1, 1);
+    document.layers.push(Layer::new("L", [0, 0, height as i32, width as i32]).channel(0, 1, compressed));
+    document.to_bytes()
+}
+
+#[test]
+fn truncated_literal_packets_fail_instead_of_panicking() {
+    // every row announces a literal of 4 bytes but carries 2 of them
+    let data = rle_rgb(4, 1, &[3, 3, 3], &[3, 1, 2, 3, 3, 4, 3, 5, 6]);
+    let psd = parse_psd(&data).unwrap();
+    assert!(matches!(psd.image_data().try_raw_data(), Err(PsdError::RleSizeMismatch { expected: 4, .. })));
+    assert!(psd.to_rgb8().is_err());
+}
+
+#[test]
+fn no_op_control_bytes_are_skipped_in_image_data() {
+    // a run of three 7, the -128 no-op, then a literal 8
+    let row = [0xfe, 7, 0x80, 0, 8];
+    let mut packets = Vec::new();
+    (0..3).for_each(|_| packets.extend_from_slice(&row));
+    let data = rle_rgb(4, 1, &[5, 5, 5], &packets);
+    let psd = parse_psd(&data).unwrap();
+    for channel in psd.image_data().try_raw_data().unwrap() {
+        assert_eq!(&channel[..], [7, 7, 7, 8]);
+    }
+}
+
+#[test]
+fn no_op_control_bytes_are_skipped_in_layer_channels() {
+    // rows of 4 pixels: a no-op between two runs, then one leading a literal
+    let compressed = [0, 5, 0, 6, 0xff, 1, 0x80, 0xff, 2, 0x80, 3, 3, 4, 5, 6];
+    let data = rle_gray_layer(4, 2, &compressed);
+    let psd = parse_psd(&data).unwrap();
+    let layer = psd.layer_information().find_layer("L").unwrap();
+    assert_eq!(layer.channel_info()[0].try_raw_data().unwrap(), [1, 1, 2, 2, 3, 4, 5, 6]);
+}
+
+/// Rows of 300 pixels mixing long runs, long literals, short runs that stay literal, and a run cut off by the end of the row.
+/// `seed` tells the rows of different channels apart.
+fn sample_rows(height: usize, seed: usize) -> Vec<Vec<u8>> {
+    (0..height)
+        .map(|y| {
+            let mut row = vec![(y + seed) as u8; 130];
+            row.extend((0..140).map(|x| (x * 7 + y + seed) as u8));
+            row.extend_from_slice(&[1, 1, 2, 2, 3, 3, 4]);
+            row.resize(300, 9);
+            row
+        })
+        .collect()
+}
+
+#[test]
+fn encoded_rows_decode_back_to_the_input() {
+    for version in [Version::Psd, Version::Psb] {
+        let (width, height) = (300, 2);
+        let channels: Vec<_> = (0..3).map(|channel| sample_rows(height, channel * 50)).collect();
+        let rows: Vec<&[u8]> = channels.iter().flatten().map(Vec::as_slice).collect();
+        let compressed = encode_rle(&rows, version);
+        let data = rle_rgb_document(version, width as u32, height as u32, &compressed);
+        let psd = parse_psd(&data).unwrap();
+        assert_eq!(psd.header().version(), version);
+        let decoded = psd.image_data().try_raw_data().unwrap();
+        for (channel, expected) in decoded.iter().zip(&channels) {
+            assert_eq!(channel[..], expected.concat()[..], "{:?}", version);
+        }
+    }
+}
+
+#[test]
+fn encoded_layer_channel_decodes_back_to_the_input() {
+    let rows = sample_rows(2, 0);
+    let compressed = encode_rle(&[&rows[0], &rows[1]], Version::Psd);
+    let data = rle_gray_layer(300, 2, &compressed);
+    let psd = parse_psd(&data).unwrap();
+    let layer = psd.layer_information().find_layer("L").unwrap();
+    assert_eq!(layer.channel_info()[0].try_raw_data().unwrap(), &rows.concat()[..]);
+}
+
+#[test]
+fn truncated_transparency_masks_fail_the_content_trim() {
+    let mut document = Document::new(4, 1, 1, 1);
+    // a literal of 4 bytes carrying 2 of them in the mask, a run of 4 in the gray channel
+    let layer = Layer::new("L", [0, 0, 1, 4]).channel(-1, 1, &rle_rows(&[3], &[3, 0, 255])).channel(0, 1, &rle_rows(&[2], &[0xfd, 9]));
+    document.layers.push(layer);
+    let data = document.to_bytes();
+    let psd = parse_psd(&data).unwrap();
+    let layer = psd.layer_information().find_layer("L").unwrap();
+    assert!(matches!(layer.content_trim(), Err(PsdError::RleSizeMismatch { expected: 4, .. })));
+}